@@ -0,0 +1,43 @@
+//! Compiles every entry point of `src/compute_shader.slang` to its own SPIR-V module in `OUT_DIR`
+//! with slangc, or the binary in `SLANGC`. Without slangc the Slang kernels are left out and the
+//! benchmark reports them as skipped. Each module gets a stamp of the source it came from, see
+//! `slang::is_fresh`.
+
+// Build scripts can't use the library target, so the modules they share are included directly
+#[path = "src/hash.rs"]
+mod hash;
+#[allow(dead_code)]
+#[path = "src/slang.rs"]
+mod slang;
+
+use slang::{SlangCompiler, SlangError};
+use std::path::{Path, PathBuf};
+
+fn main() {
+    let source = Path::new("src/compute_shader.slang");
+    println!("cargo:rerun-if-changed={}", source.display());
+    println!("cargo:rerun-if-changed=src/slang.rs");
+    println!("cargo:rerun-if-changed=src/hash.rs");
+    println!("cargo:rerun-if-env-changed=SLANGC");
+
+    let dir = PathBuf::from(std::env::var_os("OUT_DIR").unwrap()).join("slang");
+    std::fs::create_dir_all(&dir).expect("unable to create the slang output directory");
+    let compiler = SlangCompiler {
+        binary: std::env::var_os("SLANGC").map_or("slangc".into(), PathBuf::from),
+        ..Default::default()
+    };
+    let contents = std::fs::read_to_string(source).expect("unable to read slang source");
+    for entry_point in slang::entry_points(&contents) {
+        let output = slang::module_path(&dir, entry_point);
+        match compiler.compile(source, entry_point, &output) {
+            Ok(()) => slang::write_stamp(source, &output),
+            Err(err @ SlangError::Spawn(..)) => {
+                // Don't leave modules from an older source behind
+                let _ = std::fs::remove_dir_all(&dir);
+                println!("cargo:warning={err}, the Slang kernels are skipped");
+                return;
+            }
+            Err(err) => panic!("{err}"),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{for_, Hit, Ray, Triangle};
+use spirv_std::glam::*;
+
+/// Max depth of the traversal stack, the builder never makes a tree deeper than this.
+pub const BVH_STACK_SIZE: usize = 32;
+
+/// Max number of triangles in a leaf.
+pub const BVH_LEAF_SIZE: usize = 4;
+
+/// Flattened BVH node, as laid out in the GPU node buffer (32 bytes).
+///
+/// `min.w` holds the bits of the left child index for interior nodes (the right child is always
+/// right after it) or of the first triangle index for leaves. `max.w` holds the bits of the
+/// triangle count, which is 0 for interior nodes.
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+pub struct BvhNode {
+    pub min: Vec4,
+    pub max: Vec4,
+}
+
+impl BvhNode {
+    pub fn new(min: Vec3, max: Vec3, left_first: u32, count: u32) -> Self {
+        BvhNode {
+            min: min.extend(f32::from_bits(left_first)),
+            max: max.extend(f32::from_bits(count)),
+        }
+    }
+
+    pub fn left_first(&self) -> u32 {
+        self.min.w.to_bits()
+    }
+
+    pub fn count(&self) -> u32 {
+        self.max.w.to_bits()
+    }
+
+    /// Slab test against the node bounds, limited to the `t_min..t_max` range of the ray.
+    pub fn hit(&self, ray: Ray, inv_dir: Vec3) -> bool {
+        let t0 = (self.min.truncate() - ray.origin) * inv_dir;
+        let t1 = (self.max.truncate() - ray.origin) * inv_dir;
+        let t_near = t0.min(t1).max_element().max(ray.t_min);
+        let t_far = t0.max(t1).min_element().min(ray.t_max);
+        t_near <= t_far
+    }
+}
+
+impl Triangle {
+    /// Triangles are stored as 3 consecutive `Vec4`s in the GPU triangle buffer, `w` is unused.
+    pub fn load(triangles: &[Vec4], index: u32) -> Self {
+        let i = index as usize * 3;
+        Triangle {
+            a: triangles[i].truncate(),
+            b: triangles[i + 1].truncate(),
+            c: triangles[i + 2].truncate(),
+        }
+    }
+}
+
+/// Returns the closest hit, `ray.t_max` is pulled in to each hit found on the way so only closer
+/// nodes and triangles are tested after it.
+pub fn closest_hit(nodes: &[BvhNode], triangles: &[Vec4], mut ray: Ray) -> Hit {
+    let inv_dir = 1.0 / ray.direction;
+    let mut closest = Hit::MISS;
+
+    let mut stack = [0u32; BVH_STACK_SIZE];
+    let mut stack_len = 1;
+
+    while stack_len > 0 {
+        stack_len -= 1;
+        let node = nodes[stack[stack_len] as usize];
+        if node.hit(ray, inv_dir) {
+            let left_first = node.left_first();
+            let count = node.count();
+            if count > 0 {
+                for_!((let mut i = 0; i < count; i += 1) {
+                    let hit = Triangle::load(triangles, left_first + i).intersect(ray);
+                    if hit.is_hit() {
+                        closest = hit;
+                        ray.t_max = hit.t;
+                    }
+                });
+            } else {
+                stack[stack_len] = left_first + 1;
+                stack[stack_len + 1] = left_first;
+                stack_len += 2;
+            }
+        }
+    }
+
+    closest
+}
+
+/// CPU side BVH builder, produces the flattened node and triangle buffers used by
+/// [`closest_hit`].
+#[cfg(not(target_arch = "spirv"))]
+pub struct Bvh {
+    pub nodes: Vec<BvhNode>,
+    /// Triangles reordered so that each leaf references a contiguous range.
+    pub triangles: Vec<Vec4>,
+}
+
+#[cfg(not(target_arch = "spirv"))]
+impl Bvh {
+    /// Builds a BVH by splitting at the centroid midpoint of the largest axis, falling back to the
+    /// median when the midpoint doesn't separate anything.
+    pub fn build(triangles: &[Triangle]) -> Self {
+        assert!(!triangles.is_empty(), "can't build a BVH without triangles");
+
+        let mut bvh = Bvh {
+            nodes: vec![BvhNode::default()],
+            triangles: Vec::with_capacity(triangles.len() * 3),
+        };
+        let mut indices = (0..triangles.len()).collect::<Vec<_>>();
+        bvh.build_node(triangles, &mut indices, 0, 1);
+        bvh
+    }
+
+    fn build_node(
+        &mut self,
+        triangles: &[Triangle],
+        indices: &mut [usize],
+        node: usize,
+        depth: usize,
+    ) {
+        let (min, max) = indices.iter().fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(-f32::MAX)),
+            |(min, max), &i| {
+                let t = &triangles[i];
+                (
+                    min.min(t.a).min(t.b).min(t.c),
+                    max.max(t.a).max(t.b).max(t.c),
+                )
+            },
+        );
+
+        // Each level down can grow the traversal stack by one.
+        if indices.len() <= BVH_LEAF_SIZE || depth + 1 >= BVH_STACK_SIZE {
+            let first = (self.triangles.len() / 3) as u32;
+            for &i in indices.iter() {
+                let t = &triangles[i];
+                self.triangles
+                    .extend([t.a.extend(0.0), t.b.extend(0.0), t.c.extend(0.0)]);
+            }
+            self.nodes[node] = BvhNode::new(min, max, first, indices.len() as u32);
+            return;
+        }
+
+        let centroid = |i: usize| (triangles[i].a + triangles[i].b + triangles[i].c) / 3.0;
+        let (cmin, cmax) = indices.iter().fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(-f32::MAX)),
+            |(min, max), &i| (min.min(centroid(i)), max.max(centroid(i))),
+        );
+        let extent = cmax - cmin;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        let split = (cmin[axis] + cmax[axis]) * 0.5;
+
+        let mut mid = partition(indices, |i| centroid(i)[axis] < split);
+        if mid == 0 || mid == indices.len() {
+            mid = indices.len() / 2;
+            indices.select_nth_unstable_by(mid, |&a, &b| {
+                centroid(a)[axis].total_cmp(&centroid(b)[axis])
+            });
+        }
+
+        let left = self.nodes.len();
+        self.nodes.push(BvhNode::default());
+        self.nodes.push(BvhNode::default());
+        self.nodes[node] = BvhNode::new(min, max, left as u32, 0);
+
+        let (left_indices, right_indices) = indices.split_at_mut(mid);
+        self.build_node(triangles, left_indices, left, depth + 1);
+        self.build_node(triangles, right_indices, left + 1, depth + 1);
+    }
+}
+
+/// Moves the elements matching `pred` to the front, returns how many there are.
+#[cfg(not(target_arch = "spirv"))]
+fn partition(indices: &mut [usize], pred: impl Fn(usize) -> bool) -> usize {
+    let mut mid = 0;
+    for i in 0..indices.len() {
+        if pred(indices[i]) {
+            indices.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
@@ -0,0 +1,181 @@
+//! Triangle and ray pairs at the edges of what [`Triangle::intersect`] handles: degenerate
+//! triangles, rays parallel to the plane (`inv_det` infinite), NaN and infinite rays, hits on
+//! edges and vertices, and back faces. Every shader runs them with `main_intersect` to see how it
+//! handles the IEEE special cases compared to the CPU.
+
+use crate::{for_, Ray, Triangle};
+use spirv_std::glam::*;
+
+/// Vec4s per case in the `main_intersect` input: `a`, `b`, `c`, `(origin, t_min)` and
+/// `(direction, t_max)`.
+pub const CASE_LEN: usize = 5;
+/// Floats per case in the `main_intersect` output: `t`, `u`, `v` and `front_face` as 1 or 0.
+pub const HIT_LEN: usize = 4;
+
+pub struct EdgeCase {
+    pub name: &'static str,
+    pub triangle: Triangle,
+    pub ray: Ray,
+}
+
+impl EdgeCase {
+    pub fn pack(&self) -> [Vec4; CASE_LEN] {
+        let Triangle { a, b, c } = self.triangle;
+        let ray = self.ray;
+        [
+            a.extend(0.0),
+            b.extend(0.0),
+            c.extend(0.0),
+            ray.origin.extend(ray.t_min),
+            ray.direction.extend(ray.t_max),
+        ]
+    }
+}
+
+const A: Vec3 = Vec3::new(-1.0, -1.0, 0.0);
+const B: Vec3 = Vec3::new(1.0, -1.0, 0.0);
+const C: Vec3 = Vec3::new(-1.0, 1.0, 0.0);
+/// Faces +z, [`toward`] rays hit its front face.
+const TRIANGLE: Triangle = Triangle { a: A, b: B, c: C };
+const DOWN: Vec3 = Vec3::new(0.0, 0.0, -1.0);
+
+/// A ray from `z = 1` toward `TRIANGLE` that crosses the plane at `(x, y)`, at `t = 1`.
+const fn toward(x: f32, y: f32) -> Ray {
+    ray(Vec3::new(x, y, 1.0), DOWN)
+}
+
+const fn ray(origin: Vec3, direction: Vec3) -> Ray {
+    Ray {
+        origin,
+        direction,
+        t_min: 0.0,
+        t_max: f32::MAX,
+    }
+}
+
+const fn case(name: &'static str, triangle: Triangle, ray: Ray) -> EdgeCase {
+    EdgeCase {
+        name,
+        triangle,
+        ray,
+    }
+}
+
+pub const EDGE_CASES: [EdgeCase; 22] = [
+    case("inside", TRIANGLE, toward(-0.5, -0.5)),
+    case(
+        "back face",
+        TRIANGLE,
+        ray(Vec3::new(-0.5, -0.5, -1.0), Vec3::Z),
+    ),
+    case("outside", TRIANGLE, toward(0.5, 0.5)),
+    case("edge ab", TRIANGLE, toward(0.0, -1.0)),
+    case("edge bc", TRIANGLE, toward(0.0, 0.0)),
+    case("edge ca", TRIANGLE, toward(-1.0, 0.0)),
+    case("vertex a", TRIANGLE, toward(-1.0, -1.0)),
+    case("vertex b", TRIANGLE, toward(1.0, -1.0)),
+    case("vertex c", TRIANGLE, toward(-1.0, 1.0)),
+    case(
+        "collinear",
+        Triangle {
+            a: A,
+            b: Vec3::new(0.0, -1.0, 0.0),
+            c: B,
+        },
+        toward(0.0, -0.5),
+    ),
+    case("point", Triangle { a: A, b: A, c: A }, toward(-1.0, -1.0)),
+    case(
+        "parallel above the plane",
+        TRIANGLE,
+        ray(Vec3::new(-0.5, -0.5, 1.0), Vec3::X),
+    ),
+    case(
+        "parallel in the plane",
+        TRIANGLE,
+        ray(Vec3::new(-2.0, -0.5, 0.0), Vec3::X),
+    ),
+    case(
+        "zero direction",
+        TRIANGLE,
+        ray(Vec3::new(-0.5, -0.5, 1.0), Vec3::ZERO),
+    ),
+    case(
+        "NaN origin",
+        TRIANGLE,
+        ray(Vec3::new(f32::NAN, -0.5, 1.0), DOWN),
+    ),
+    case(
+        "NaN direction",
+        TRIANGLE,
+        ray(Vec3::new(-0.5, -0.5, 1.0), Vec3::new(0.0, f32::NAN, -1.0)),
+    ),
+    case(
+        "infinite origin",
+        TRIANGLE,
+        ray(Vec3::new(-0.5, -0.5, f32::INFINITY), DOWN),
+    ),
+    case(
+        "infinite direction",
+        TRIANGLE,
+        ray(
+            Vec3::new(-0.5, -0.5, 1.0),
+            Vec3::new(0.0, 0.0, f32::NEG_INFINITY),
+        ),
+    ),
+    case(
+        "behind the origin",
+        TRIANGLE,
+        ray(Vec3::new(-0.5, -0.5, -1.0), DOWN),
+    ),
+    case(
+        "hit at t_min",
+        TRIANGLE,
+        Ray {
+            t_min: 1.0,
+            ..toward(-0.5, -0.5)
+        },
+    ),
+    case(
+        "huge triangle",
+        Triangle {
+            a: Vec3::new(-1e30, -1e30, 0.0),
+            b: Vec3::new(1e30, -1e30, 0.0),
+            c: Vec3::new(-1e30, 1e30, 0.0),
+        },
+        toward(-0.5, -0.5),
+    ),
+    case(
+        "tiny triangle",
+        Triangle {
+            a: Vec3::new(-1e-30, -1e-30, 0.0),
+            b: Vec3::new(1e-30, -1e-30, 0.0),
+            c: Vec3::new(-1e-30, 1e-30, 0.0),
+        },
+        toward(-5e-31, -5e-31),
+    ),
+];
+
+/// Intersects the first `count` cases packed in `cases`, writes [`HIT_LEN`] floats per case to
+/// `data`.
+pub fn intersect_cases(count: u32, cases: &[Vec4], data: &mut [f32]) {
+    for_!((let mut i = 0; i < count as usize; i += 1) {
+        let base = i * CASE_LEN;
+        let triangle = Triangle {
+            a: cases[base].truncate(),
+            b: cases[base + 1].truncate(),
+            c: cases[base + 2].truncate(),
+        };
+        let ray = Ray {
+            origin: cases[base + 3].truncate(),
+            direction: cases[base + 4].truncate(),
+            t_min: cases[base + 3].w,
+            t_max: cases[base + 4].w,
+        };
+        let hit = triangle.intersect(ray);
+        data[i * HIT_LEN] = hit.t;
+        data[i * HIT_LEN + 1] = hit.u;
+        data[i * HIT_LEN + 2] = hit.v;
+        data[i * HIT_LEN + 3] = if hit.front_face { 1.0 } else { 0.0 };
+    });
+}
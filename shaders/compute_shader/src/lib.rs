@@ -2,16 +2,24 @@
 // HACK(eddyb) can't easily see warnings otherwise from `spirv-builder` builds.
 //#![deny(warnings)]
 
+pub mod bvh;
+pub mod edge_cases;
+pub mod micro;
+pub mod rng;
 pub mod util;
+use bvh::BvhNode;
 use glam::*;
-use spirv_std::{glam, spirv};
+use rng::{AnyRng, Rng};
+pub use spirv_std::glam;
+use spirv_std::spirv;
 
 // Note: This cfg is incorrect on its surface, it really should be "are we compiling with std", but
 // we tie #[no_std] above to the same condition, so it's fine.
 #[cfg(target_arch = "spirv")]
 use spirv_std::num_traits::Float;
-use util::hash_noise;
+use util::{hash_noise, uniform_sphere};
 
+#[derive(Clone, Copy)]
 pub struct Triangle {
     pub a: Vec3,
     pub b: Vec3,
@@ -19,7 +27,7 @@ pub struct Triangle {
 }
 
 impl Triangle {
-    fn random(rng_coord: UVec2, seed: u32) -> Self {
+    pub fn random(rng_coord: UVec2, seed: u32) -> Self {
         Triangle {
             a: vec3(
                 hash_noise(rng_coord, 0 + seed),
@@ -42,7 +50,16 @@ impl Triangle {
         }
     }
 
-    pub fn intersect(&self, ray: Ray) -> Vec3 {
+    /// Same as [`Triangle::random`] but with the values drawn from `rng`.
+    pub fn from_rng(rng: &mut impl Rng) -> Self {
+        Triangle {
+            a: rng_vec3(rng) * 2.0 - 1.0,
+            b: rng_vec3(rng) * 2.0 - 1.0,
+            c: rng_vec3(rng) * 2.0 - 1.0,
+        }
+    }
+
+    pub fn intersect(&self, ray: Ray) -> Hit {
         let e1 = self.a - self.b;
         let e2 = self.c - self.a;
         let n = e1.cross(e2);
@@ -53,21 +70,67 @@ impl Triangle {
 
         let uvt = vec3(r.dot(e2), r.dot(e1), n.dot(c)) * inv_det;
 
-        if (uvt.x > 0.0) & (uvt.y > 0.0) & (uvt.z > 0.0) & (uvt.x + uvt.y < 1.0) {
-            return uvt;
+        if (uvt.x > 0.0)
+            & (uvt.y > 0.0)
+            & (uvt.x + uvt.y < 1.0)
+            & (uvt.z > ray.t_min)
+            & (uvt.z < ray.t_max)
+        {
+            return Hit {
+                t: uvt.z,
+                u: uvt.x,
+                v: uvt.y,
+                // `n` points away from the side the (a, b, c) winding faces.
+                front_face: inv_det > 0.0,
+            };
         }
 
-        return vec3(f32::MAX, f32::MAX, f32::MAX);
+        return Hit::MISS;
     }
 }
 
+#[derive(Clone, Copy)]
+pub struct Hit {
+    pub t: f32,
+    pub u: f32,
+    pub v: f32,
+    /// The ray hit the side that the counter-clockwise (a, b, c) winding faces.
+    pub front_face: bool,
+}
+
+impl Hit {
+    pub const MISS: Hit = Hit {
+        t: f32::MAX,
+        u: 0.0,
+        v: 0.0,
+        front_face: false,
+    };
+
+    pub fn is_hit(&self) -> bool {
+        self.t < f32::MAX
+    }
+}
+
+#[derive(Clone, Copy)]
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
+    /// Hits are only reported for `t_min < t < t_max`.
+    pub t_min: f32,
+    pub t_max: f32,
 }
 
 impl Ray {
-    fn random(rng_coord: UVec2, seed: u32) -> Self {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Ray {
+            origin,
+            direction,
+            t_min: 0.0,
+            t_max: f32::MAX,
+        }
+    }
+
+    pub fn random(rng_coord: UVec2, seed: u32) -> Self {
         let origin = vec3(
             hash_noise(rng_coord, 0 + seed),
             hash_noise(rng_coord, 1 + seed),
@@ -80,36 +143,259 @@ impl Ray {
             hash_noise(rng_coord, 5 + seed),
         ) * 2.0
             - 1.0;
-        Ray {
-            origin,
-            direction: direction.normalize(),
+        Ray::new(origin, direction.normalize())
+    }
+
+    /// Same as [`Ray::random`] but with the values drawn from `rng`.
+    pub fn from_rng(rng: &mut impl Rng) -> Self {
+        let origin = rng_vec3(rng) * 2.0 - 1.0;
+        let direction = rng_vec3(rng) * 2.0 - 1.0;
+        Ray::new(origin, direction.normalize())
+    }
+
+    /// Same as [`Ray::from_rng`] but with the direction drawn uniformly from the unit sphere,
+    /// normalizing a point in the cube biases the directions toward its corners.
+    pub fn from_rng_sphere(rng: &mut impl Rng) -> Self {
+        let origin = rng_vec3(rng) * 2.0 - 1.0;
+        let u = rng.next_f32();
+        let v = rng.next_f32();
+        Ray::new(origin, uniform_sphere(vec2(u, v)))
+    }
+
+    /// Primary ray of a pinhole camera at (0, 0, -3) looking down +z. The image plane is 1x1 at a
+    /// distance of 1, so the front face of the -1..1 cube exactly fills a `size * size` image.
+    /// `coord` (0, 0) is the top left pixel.
+    pub fn camera(coord: UVec2, size: u32) -> Self {
+        let uv = (coord.as_vec2() + 0.5) / size as f32;
+        let direction = vec3(uv.x - 0.5, 0.5 - uv.y, 1.0);
+        Ray::new(vec3(0.0, 0.0, -3.0), direction.normalize())
+    }
+}
+
+fn rng_vec3(rng: &mut impl Rng) -> Vec3 {
+    let x = rng.next_f32();
+    let y = rng.next_f32();
+    let z = rng.next_f32();
+    vec3(x, y, z)
+}
+
+/// What the workloads accumulate over all of their rays, written to the start of the output
+/// buffer in field order.
+#[derive(Clone, Copy)]
+#[cfg_attr(not(target_arch = "spirv"), derive(Debug, PartialEq))]
+pub struct Stats {
+    /// Sum of `sin(min(t, 100))`, misses included.
+    pub sum: f32,
+    /// Nearest hit over all rays, `f32::MAX` if nothing was hit.
+    pub nearest_t: f32,
+    /// Kept as a float so it can share the output buffer, exact up to 2^24 hits.
+    pub hit_count: f32,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stats {
+    pub const LEN: usize = 3;
+
+    pub fn new() -> Self {
+        Stats {
+            sum: 0.0,
+            nearest_t: f32::MAX,
+            hit_count: 0.0,
+        }
+    }
+
+    pub fn add(&mut self, hit: Hit) {
+        self.sum += hit.t.min(100.0).sin();
+        if hit.is_hit() {
+            self.nearest_t = self.nearest_t.min(hit.t);
+            self.hit_count += 1.0;
+        }
+    }
+
+    pub fn write(&self, data: &mut [f32], index: usize) {
+        data[index] = self.sum;
+        data[index + 1] = self.nearest_t;
+        data[index + 2] = self.hit_count;
+    }
+
+    pub fn read(data: &[f32], index: usize) -> Self {
+        Stats {
+            sum: data[index],
+            nearest_t: data[index + 1],
+            hit_count: data[index + 2],
         }
     }
 }
 
-pub fn compute(size: u32) -> f32 {
-    let mut sum = 0.0;
-    //for x in 0..size {
-    //    for y in 0..size {
-    //        let coord = uvec2(x, y);
-    //        let tri = Triangle::random(coord, 0);
-    //        let ray = Ray::random(coord, 9);
-    //        sum += tri.intersect(ray).y.min(100.0).sin();
-    //    }
-    //}
+/// Kahan summation, `compensation` carries the low bits lost from `sum` to the next
+/// [`KahanSum::add`].
+#[derive(Clone, Copy, Default)]
+pub struct KahanSum {
+    pub sum: f32,
+    pub compensation: f32,
+}
+
+impl KahanSum {
+    pub fn add(&mut self, value: f32) {
+        let term = value - self.compensation;
+        let sum = self.sum + term;
+        self.compensation = (sum - self.sum) - term;
+        self.sum = sum;
+    }
+}
+
+pub fn compute(size: u32) -> Stats {
+    let mut stats = Stats::new();
 
     for_!((let mut x = 0; x < size; x += 1) {
         for_!((let mut y = 0; y < size; y += 1) {
             let coord = uvec2(x, y);
             let tri = Triangle::random(coord, 0);
             let ray = Ray::random(coord, 9);
-            sum += tri.intersect(ray).y.min(100.0).sin();
+            stats.add(tri.intersect(ray));
+        });
+    });
+
+    return stats;
+}
+
+/// [`compute`] with the sum accumulated with [`KahanSum`].
+pub fn compute_kahan(size: u32) -> Stats {
+    let mut stats = Stats::new();
+    let mut kahan = KahanSum::default();
+
+    for_!((let mut x = 0; x < size; x += 1) {
+        for_!((let mut y = 0; y < size; y += 1) {
+            let coord = uvec2(x, y);
+            let tri = Triangle::random(coord, 0);
+            let ray = Ray::random(coord, 9);
+            let hit = tri.intersect(ray);
+            kahan.add(hit.t.min(100.0).sin());
+            stats.add(hit);
+        });
+    });
+    stats.sum = kahan.sum;
+
+    return stats;
+}
+
+/// The sum of [`compute`] with each term's `sin` taken and accumulated in f64, the reference its
+/// f32 sums are measured against. The rays and hits are still computed in f32.
+#[cfg(not(target_arch = "spirv"))]
+pub fn compute_sum_f64(size: u32) -> f64 {
+    let mut sum = 0.0;
+
+    for_!((let mut x = 0; x < size; x += 1) {
+        for_!((let mut y = 0; y < size; y += 1) {
+            let coord = uvec2(x, y);
+            let hit = Triangle::random(coord, 0).intersect(Ray::random(coord, 9));
+            sum += (hit.t.min(100.0) as f64).sin();
         });
     });
 
     return sum;
 }
 
+/// [`compute`] with `for in` loops over ranges, to compare against `for_!`.
+pub fn compute_for_in(size: u32) -> Stats {
+    let mut stats = Stats::new();
+
+    for x in 0..size {
+        for y in 0..size {
+            let coord = uvec2(x, y);
+            let tri = Triangle::random(coord, 0);
+            let ray = Ray::random(coord, 9);
+            stats.add(tri.intersect(ray));
+        }
+    }
+
+    return stats;
+}
+
+/// [`compute`] with `while` loops.
+pub fn compute_while(size: u32) -> Stats {
+    let mut stats = Stats::new();
+
+    let mut x = 0;
+    while x < size {
+        let mut y = 0;
+        while y < size {
+            let coord = uvec2(x, y);
+            let tri = Triangle::random(coord, 0);
+            let ray = Ray::random(coord, 9);
+            stats.add(tri.intersect(ray));
+            y += 1;
+        }
+        x += 1;
+    }
+
+    return stats;
+}
+
+pub fn compute_bvh(size: u32, nodes: &[BvhNode], triangles: &[Vec4]) -> Stats {
+    let mut stats = Stats::new();
+
+    for_!((let mut x = 0; x < size; x += 1) {
+        for_!((let mut y = 0; y < size; y += 1) {
+            let coord = uvec2(x, y);
+            let ray = Ray::random(coord, 9);
+            stats.add(bvh::closest_hit(nodes, triangles, ray));
+        });
+    });
+
+    return stats;
+}
+
+/// Same as [`compute`] but with the generator picked by `rng_kind`, gives the same result as
+/// [`compute`] with [`rng::RNG_HASH`]. With `sphere_rays` the ray directions come from
+/// [`Ray::from_rng_sphere`].
+pub fn compute_rng(size: u32, rng_kind: u32, sphere_rays: bool) -> Stats {
+    let mut stats = Stats::new();
+
+    for_!((let mut x = 0; x < size; x += 1) {
+        for_!((let mut y = 0; y < size; y += 1) {
+            let mut rng = AnyRng::new(rng_kind, uvec2(x, y), 0);
+            let tri = Triangle::from_rng(&mut rng);
+            let ray = if sphere_rays {
+                Ray::from_rng_sphere(&mut rng)
+            } else {
+                Ray::from_rng(&mut rng)
+            };
+            stats.add(tri.intersect(ray));
+        });
+    });
+
+    return stats;
+}
+
+/// Same as [`compute_bvh`] but with coherent camera rays, the `t` of each pixel's hit is written to
+/// `image[image_offset + y * size + x]`.
+pub fn compute_camera(
+    size: u32,
+    nodes: &[BvhNode],
+    triangles: &[Vec4],
+    image: &mut [f32],
+    image_offset: usize,
+) -> Stats {
+    let mut stats = Stats::new();
+
+    for_!((let mut x = 0; x < size; x += 1) {
+        for_!((let mut y = 0; y < size; y += 1) {
+            let coord = uvec2(x, y);
+            let hit = bvh::closest_hit(nodes, triangles, Ray::camera(coord, size));
+            image[image_offset + y as usize * size as usize + x as usize] = hit.t;
+            stats.add(hit);
+        });
+    });
+
+    return stats;
+}
+
 // LocalSize/numthreads of (x = 1, y = 1, z = 1)
 #[spirv(compute(threads(1)))]
 pub fn main(
@@ -118,5 +404,126 @@ pub fn main(
     #[spirv(uniform, descriptor_set = 0, binding = 1)] settings: &UVec4,
 ) {
     let index = id.x as usize;
-    data[index] = compute(settings.x);
+    compute(settings.x).write(data, index);
+}
+
+// `main` with the size as specialization constant 0, for `--settings-variants`
+// LocalSize/numthreads of (x = 1, y = 1, z = 1)
+#[spirv(compute(threads(1)))]
+pub fn main_constant(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] data: &mut [f32],
+    #[spirv(spec_constant(id = 0, default = 0))] size: u32,
+) {
+    let index = id.x as usize;
+    compute(size).write(data, index);
+}
+
+// LocalSize/numthreads of (x = 1, y = 1, z = 1)
+#[spirv(compute(threads(1)))]
+pub fn main_kahan(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] data: &mut [f32],
+    #[spirv(uniform, descriptor_set = 0, binding = 1)] settings: &UVec4,
+) {
+    let index = id.x as usize;
+    compute_kahan(settings.x).write(data, index);
+}
+
+// LocalSize/numthreads of (x = 1, y = 1, z = 1)
+#[spirv(compute(threads(1)))]
+pub fn main_for_in(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] data: &mut [f32],
+    #[spirv(uniform, descriptor_set = 0, binding = 1)] settings: &UVec4,
+) {
+    let index = id.x as usize;
+    compute_for_in(settings.x).write(data, index);
+}
+
+// LocalSize/numthreads of (x = 1, y = 1, z = 1)
+#[spirv(compute(threads(1)))]
+pub fn main_while(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] data: &mut [f32],
+    #[spirv(uniform, descriptor_set = 0, binding = 1)] settings: &UVec4,
+) {
+    let index = id.x as usize;
+    compute_while(settings.x).write(data, index);
+}
+
+// LocalSize/numthreads of (x = 1, y = 1, z = 1)
+#[spirv(compute(threads(1)))]
+pub fn main_bvh(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] data: &mut [f32],
+    #[spirv(uniform, descriptor_set = 0, binding = 1)] settings: &UVec4,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] triangles: &[Vec4],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] nodes: &[BvhNode],
+) {
+    let index = id.x as usize;
+    compute_bvh(settings.x, nodes, triangles).write(data, index);
+}
+
+// LocalSize/numthreads of (x = 1, y = 1, z = 1)
+#[spirv(compute(threads(1)))]
+pub fn main_camera(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] data: &mut [f32],
+    #[spirv(uniform, descriptor_set = 0, binding = 1)] settings: &UVec4,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] triangles: &[Vec4],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] nodes: &[BvhNode],
+) {
+    let index = id.x as usize;
+    let stats = compute_camera(settings.x, nodes, triangles, data, index + Stats::LEN);
+    stats.write(data, index);
+}
+
+// LocalSize/numthreads of (x = 1, y = 1, z = 1)
+#[spirv(compute(threads(1)))]
+pub fn main_rng(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] data: &mut [f32],
+    #[spirv(uniform, descriptor_set = 0, binding = 1)] settings: &UVec4,
+) {
+    let index = id.x as usize;
+    compute_rng(settings.x, settings.y, settings.z != 0).write(data, index);
+}
+
+// LocalSize/numthreads of (x = 1, y = 1, z = 1)
+#[spirv(compute(threads(1)))]
+pub fn main_intersect(
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] data: &mut [f32],
+    #[spirv(uniform, descriptor_set = 0, binding = 1)] settings: &UVec4,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] cases: &[Vec4],
+) {
+    edge_cases::intersect_cases(settings.x, cases, data);
+}
+
+// LocalSize/numthreads of (x = 1, y = 1, z = 1)
+#[spirv(compute(threads(1)))]
+pub fn main_micro(
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] data: &mut [f32],
+    #[spirv(uniform, descriptor_set = 0, binding = 1)] settings: &UVec4,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] inputs: &[f32],
+) {
+    micro::run(settings.y, settings.x, inputs, data);
+}
+
+// LocalSize/numthreads of (x = 1, y = 1, z = 1)
+#[spirv(compute(threads(1)))]
+pub fn main_rng_dump(
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] data: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 1)] settings: &UVec4,
+) {
+    rng::dump(settings.x, data);
+}
+
+// LocalSize/numthreads of (x = 1, y = 1, z = 1)
+#[spirv(compute(threads(1)))]
+pub fn main_hash_dump(
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] data: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 1)] settings: &UVec4,
+) {
+    util::hash_dump(settings.x, data);
 }
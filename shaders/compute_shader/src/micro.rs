@@ -0,0 +1,103 @@
+//! Microbenchmarks of the math the kernels use, each one evaluated over a sampled domain so its
+//! error can be measured against an f64 reference.
+
+use crate::{
+    for_,
+    util::{acos_approx, exp, pow, saturate, smoothstep},
+};
+use spirv_std::glam::*;
+
+#[cfg(target_arch = "spirv")]
+use spirv_std::num_traits::Float;
+
+/// Function names, the index is the kind passed to [`eval`] (and `settings.y` of the
+/// `main_micro` kernel). `copy` returns its input, it times the loop and the store on their own.
+pub const MICRO_NAMES: [&str; 9] = [
+    "copy",
+    "sin",
+    "division",
+    "normalize",
+    "acos_approx",
+    "pow",
+    "exp",
+    "smoothstep",
+    "saturate",
+];
+
+pub const MICRO_COPY: u32 = 0;
+pub const MICRO_SIN: u32 = 1;
+pub const MICRO_DIVISION: u32 = 2;
+pub const MICRO_NORMALIZE: u32 = 3;
+pub const MICRO_ACOS_APPROX: u32 = 4;
+pub const MICRO_POW: u32 = 5;
+pub const MICRO_EXP: u32 = 6;
+pub const MICRO_SMOOTHSTEP: u32 = 7;
+pub const MICRO_SATURATE: u32 = 8;
+
+/// Domain of each function, sampled by [`input`].
+pub const MICRO_DOMAINS: [(f32, f32); 9] = [
+    (-1.0, 1.0),
+    (-100.0, 100.0),
+    (0.1, 10.0),
+    (-10.0, 10.0),
+    (-1.0, 1.0),
+    (0.0, 1.0),
+    (-10.0, 10.0),
+    (-0.5, 1.5),
+    (-2.0, 2.0),
+];
+
+/// Added to `x` to get the vector [`eval`] normalizes, `normalize(vec3(x, 0.5, -0.25)).x`.
+const NORMALIZE_YZ: Vec2 = Vec2::new(0.5, -0.25);
+
+/// Sample `i` of `count`, spread evenly over the domain of function `kind` without its ends.
+pub fn input(kind: u32, i: u32, count: u32) -> f32 {
+    let (start, end) = MICRO_DOMAINS[kind as usize];
+    start + (end - start) * ((i as f32 + 0.5) / count as f32)
+}
+
+/// Function `kind` at `x`, in f32 like the kernels.
+pub fn eval(kind: u32, x: f32) -> f32 {
+    match kind {
+        MICRO_SIN => x.sin(),
+        MICRO_DIVISION => 1.0 / x,
+        MICRO_NORMALIZE => vec3(x, NORMALIZE_YZ.x, NORMALIZE_YZ.y).normalize().x,
+        MICRO_ACOS_APPROX => acos_approx(x),
+        MICRO_POW => pow(Vec3::splat(x), 2.2).x,
+        MICRO_EXP => exp(Vec3::splat(x)).x,
+        MICRO_SMOOTHSTEP => smoothstep(0.0, 1.0, x),
+        MICRO_SATURATE => saturate(x),
+        _ => x,
+    }
+}
+
+/// What [`eval`] approximates, in f64.
+#[cfg(not(target_arch = "spirv"))]
+pub fn reference(kind: u32, x: f32) -> f64 {
+    let x = x as f64;
+    match kind {
+        MICRO_SIN => x.sin(),
+        MICRO_DIVISION => 1.0 / x,
+        MICRO_NORMALIZE => {
+            let (y, z) = (NORMALIZE_YZ.x as f64, NORMALIZE_YZ.y as f64);
+            x / (x * x + y * y + z * z).sqrt()
+        }
+        MICRO_ACOS_APPROX => x.acos(),
+        MICRO_POW => x.powf(2.2),
+        MICRO_EXP => x.exp(),
+        MICRO_SMOOTHSTEP => {
+            let x = x.clamp(0.0, 1.0);
+            x * x * (3.0 - 2.0 * x)
+        }
+        MICRO_SATURATE => x.clamp(0.0, 1.0),
+        _ => x,
+    }
+}
+
+/// Writes `eval(kind, inputs[i])` to `data[i]` for `0 <= i < count`. The inputs come from a
+/// buffer so the GPU evaluates exactly the same values as the CPU.
+pub fn run(kind: u32, count: u32, inputs: &[f32], data: &mut [f32]) {
+    for_!((let mut i = 0; i < count; i += 1) {
+        data[i as usize] = eval(kind, inputs[i as usize]);
+    });
+}
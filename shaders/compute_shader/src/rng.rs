@@ -0,0 +1,271 @@
+use crate::{
+    for_,
+    util::{uhash, unormf},
+};
+use core::num::Wrapping;
+use spirv_std::glam::*;
+
+/// Generator names, the index is the kind passed to [`AnyRng::new`] (and `settings.y` of the
+/// `main_rng` kernels).
+pub const RNG_NAMES: [&str; 5] = ["hash", "pcg32", "xorshift", "sobol", "r2"];
+
+pub const RNG_HASH: u32 = 0;
+pub const RNG_PCG32: u32 = 1;
+pub const RNG_XORSHIFT: u32 = 2;
+pub const RNG_SOBOL: u32 = 3;
+pub const RNG_R2: u32 = 4;
+
+/// Number of values per sample written by [`dump`].
+pub const DUMP_DIMENSIONS: u32 = 16;
+
+pub trait Rng {
+    fn next_u32(&mut self) -> u32;
+
+    fn next_f32(&mut self) -> f32 {
+        unormf(self.next_u32())
+    }
+}
+
+/// The original `hash_noise` stream, `HashRng::new(coord, seed)` returns the same values as
+/// `hash_noise(coord, seed)`, `hash_noise(coord, seed + 1)`, ...
+pub struct HashRng {
+    x: u32,
+    frame: Wrapping<u32>,
+}
+
+impl HashRng {
+    pub fn new(coord: UVec2, seed: u32) -> Self {
+        HashRng {
+            x: coord.x,
+            frame: Wrapping(coord.y << 11u32) + Wrapping(seed),
+        }
+    }
+}
+
+impl Rng for HashRng {
+    fn next_u32(&mut self) -> u32 {
+        let x = uhash(self.x, self.frame.0);
+        self.frame += Wrapping(1u32);
+        x
+    }
+}
+
+/// PCG with a 32 bit state and the RXS-M-XS output function, there are no 64 bit integers in
+/// WGSL. From <https://jcgt.org/published/0009/03/02/>
+pub struct Pcg32 {
+    state: u32,
+}
+
+impl Pcg32 {
+    pub fn new(coord: UVec2, seed: u32) -> Self {
+        Pcg32 {
+            state: HashRng::new(coord, seed).next_u32(),
+        }
+    }
+}
+
+impl Rng for Pcg32 {
+    fn next_u32(&mut self) -> u32 {
+        pcg32(&mut self.state)
+    }
+}
+
+/// Marsaglia's xorshift32.
+pub struct Xorshift32 {
+    state: u32,
+}
+
+impl Xorshift32 {
+    pub fn new(coord: UVec2, seed: u32) -> Self {
+        let state = HashRng::new(coord, seed).next_u32();
+        // The all zero state never leaves zero.
+        Xorshift32 {
+            state: if state == 0 { 1 } else { state },
+        }
+    }
+}
+
+impl Rng for Xorshift32 {
+    fn next_u32(&mut self) -> u32 {
+        xorshift32(&mut self.state)
+    }
+}
+
+/// Owen scrambled 2D Sobol sequence, padded to more dimensions by shuffling the sample index of
+/// each pair of dimensions. From <https://www.jcgt.org/published/0009/04/01/>
+pub struct Sobol {
+    index: u32,
+    dimension: u32,
+    seed: u32,
+}
+
+impl Sobol {
+    pub fn new(coord: UVec2, seed: u32) -> Self {
+        Sobol {
+            index: sample_index(coord),
+            dimension: 0,
+            seed,
+        }
+    }
+}
+
+impl Rng for Sobol {
+    fn next_u32(&mut self) -> u32 {
+        let x = sobol(self.index, self.dimension, self.seed);
+        self.dimension += 1;
+        x
+    }
+}
+
+/// Roberts' R2 sequence in 0.32 fixed point, so it's bit exact everywhere. Each pair of dimensions
+/// gets its own random offset. From <https://extremelearning.com.au/unreasonable-effectiveness-of-quasirandom-sequences/>
+pub struct R2 {
+    index: u32,
+    dimension: u32,
+    seed: u32,
+}
+
+impl R2 {
+    pub fn new(coord: UVec2, seed: u32) -> Self {
+        R2 {
+            index: sample_index(coord),
+            dimension: 0,
+            seed,
+        }
+    }
+}
+
+impl Rng for R2 {
+    fn next_u32(&mut self) -> u32 {
+        let x = r2(self.index, self.dimension, self.seed);
+        self.dimension += 1;
+        x
+    }
+}
+
+/// Generator picked at runtime by `kind`, laid out the same as the `Rng` struct of the WGSL and
+/// Slang shaders since WGSL has no generics.
+pub struct AnyRng {
+    kind: u32,
+    index: u32,
+    state: u32,
+    dimension: u32,
+}
+
+impl AnyRng {
+    pub fn new(kind: u32, coord: UVec2, seed: u32) -> Self {
+        let (index, state) = match kind {
+            RNG_PCG32 => (0, Pcg32::new(coord, seed).state),
+            RNG_XORSHIFT => (0, Xorshift32::new(coord, seed).state),
+            RNG_SOBOL | RNG_R2 => (sample_index(coord), seed),
+            _ => {
+                let hash = HashRng::new(coord, seed);
+                (hash.x, hash.frame.0)
+            }
+        };
+        AnyRng {
+            kind,
+            index,
+            state,
+            dimension: 0,
+        }
+    }
+}
+
+impl Rng for AnyRng {
+    fn next_u32(&mut self) -> u32 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        match self.kind {
+            RNG_PCG32 => pcg32(&mut self.state),
+            RNG_XORSHIFT => xorshift32(&mut self.state),
+            RNG_SOBOL => sobol(self.index, dimension, self.state),
+            RNG_R2 => r2(self.index, dimension, self.state),
+            _ => uhash(self.index, (Wrapping(self.state) + Wrapping(dimension)).0),
+        }
+    }
+}
+
+/// Sample index of the low discrepancy sequences, consecutive `y`s are consecutive samples.
+pub fn sample_index(coord: UVec2) -> u32 {
+    (coord.x << 16u32) ^ coord.y
+}
+
+pub fn pcg32(state: &mut u32) -> u32 {
+    let s = *state;
+    *state = (Wrapping(s) * Wrapping(747796405u32) + Wrapping(2891336453u32)).0;
+    let word = (Wrapping((s >> ((s >> 28u32) + 4u32)) ^ s) * Wrapping(277803737u32)).0;
+    (word >> 22u32) ^ word
+}
+
+pub fn xorshift32(state: &mut u32) -> u32 {
+    let mut x = *state;
+    x ^= x << 13u32;
+    x ^= x >> 17u32;
+    x ^= x << 5u32;
+    *state = x;
+    x
+}
+
+/// Second dimension of the Sobol sequence, the first one is `index.reverse_bits()`.
+pub fn sobol_dim1(index: u32) -> u32 {
+    let mut result = 0;
+    let mut v = 1u32 << 31u32;
+    let mut i = index;
+    while i != 0 {
+        if (i & 1) != 0 {
+            result ^= v;
+        }
+        i >>= 1u32;
+        v ^= v >> 1u32;
+    }
+    result
+}
+
+pub fn laine_karras_permutation(x: u32, seed: u32) -> u32 {
+    let mut x = Wrapping(x) + Wrapping(seed);
+    x ^= x * Wrapping(0x6c50b47cu32);
+    x ^= x * Wrapping(0xb82f1e52u32);
+    x ^= x * Wrapping(0xc7afe638u32);
+    x ^= x * Wrapping(0x8d22f6e6u32);
+    x.0
+}
+
+pub fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+pub fn sobol(index: u32, dimension: u32, seed: u32) -> u32 {
+    let pair_seed = uhash(dimension >> 1u32, seed);
+    let shuffled = nested_uniform_scramble(index, pair_seed);
+    let x = if (dimension & 1) == 0 {
+        shuffled.reverse_bits()
+    } else {
+        sobol_dim1(shuffled)
+    };
+    nested_uniform_scramble(x, uhash(pair_seed, dimension))
+}
+
+pub fn r2(index: u32, dimension: u32, seed: u32) -> u32 {
+    // 2^32 / g and 2^32 / g^2, with g the plastic number
+    let alpha = if (dimension & 1) == 0 {
+        0xc13fa9a9u32
+    } else {
+        0x91e10da6u32
+    };
+    (Wrapping(uhash(dimension >> 1u32, seed)) + Wrapping(index) * Wrapping(alpha)).0
+}
+
+/// Writes [`DUMP_DIMENSIONS`] values of sample `i` of generator `kind` to
+/// `data[(kind * count + i) * DUMP_DIMENSIONS + dimension]`, for every generator and
+/// `0 <= i < count`. Used to compare the bits of each shader against the CPU.
+pub fn dump(count: u32, data: &mut [u32]) {
+    for_!((let mut kind = 0; kind < RNG_NAMES.len() as u32; kind += 1) {
+        for_!((let mut i = 0; i < count; i += 1) {
+            let mut rng = AnyRng::new(kind, uvec2(i >> 4u32, i & 15), 0);
+            for_!((let mut dimension = 0; dimension < DUMP_DIMENSIONS; dimension += 1) {
+                data[((kind * count + i) * DUMP_DIMENSIONS + dimension) as usize] = rng.next_u32();
+            });
+        });
+    });
+}
@@ -1,4 +1,7 @@
-use core::{f32::consts::PI, num::Wrapping};
+use core::{
+    f32::consts::{FRAC_1_PI, FRAC_PI_2, FRAC_PI_4, PI, TAU},
+    num::Wrapping,
+};
 
 #[cfg(target_arch = "spirv")]
 use spirv_std::arch::{signed_max, signed_min, unsigned_max, unsigned_min};
@@ -145,6 +148,8 @@ pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
     x * x * (3.0 - 2.0 * x)
 }
 
+/// Every multiply wraps, like it does on the GPU. A plain `*` would panic on overflow in debug
+/// builds on the CPU, `tests/hash.rs` pins the outputs so neither side can drift.
 pub fn uhash(a: u32, b: u32) -> u32 {
     let mut x =
         (Wrapping(a) * Wrapping(1597334673u32)).0 ^ (Wrapping(b) * Wrapping(3812015801u32)).0;
@@ -157,17 +162,6 @@ pub fn uhash(a: u32, b: u32) -> u32 {
     x
 }
 
-//pub fn uhash(a: u32, b: u32) -> u32 {
-//    let mut x = (a * 1597334673u32) ^ (b * 3812015801u32);
-//    // from https://nullprogram.com/blog/2018/07/31/
-//    x = x ^ (x >> 16u32);
-//    x = x * 0x7feb352du32;
-//    x = x ^ (x >> 15u32);
-//    x = x * 0x846ca68bu32;
-//    x = x ^ (x >> 16u32);
-//    x
-//}
-
 pub fn unormf(n: u32) -> f32 {
     n as f32 * (1.0 / 0xffffffffu32 as f32)
 }
@@ -177,6 +171,97 @@ pub fn hash_noise(ucoord: UVec2, frame: u32) -> f32 {
     unormf(urnd)
 }
 
+/// Blocks written by [`hash_dump`], in order.
+pub const HASH_NAMES: [&str; 3] = ["uhash", "hash_noise", "unormf"];
+
+/// Arguments of sample `i` of [`hash_dump`]. `a` is spread over the whole `u32` range so every
+/// multiply in [`uhash`] overflows somewhere.
+pub fn hash_dump_args(i: u32) -> UVec2 {
+    uvec2((Wrapping(i) * Wrapping(0x9e3779b9u32)).0, !i)
+}
+
+/// Writes block `kind` of [`HASH_NAMES`] for sample `i` to `data[kind * count + i]`, for
+/// `0 <= i < count`: `uhash(a, b)`, the bits of `hash_noise(uvec2(a, i >> 4), i & 15)` and the
+/// bits of `unormf(a)`, with `(a, b)` from [`hash_dump_args`]. Used to compare the bits of each
+/// shader against the CPU.
+pub fn hash_dump(count: u32, data: &mut [u32]) {
+    crate::for_!((let mut i = 0; i < count; i += 1) {
+        let args = hash_dump_args(i);
+        data[i as usize] = uhash(args.x, args.y);
+        data[(count + i) as usize] = hash_noise(uvec2(args.x, i >> 4u32), i & 15).to_bits();
+        data[(2 * count + i) as usize] = unormf(args.x).to_bits();
+    });
+}
+
+// Sampling functions take `u` uniform in 0..1 and are from
+// <https://pbr-book.org/3ed-2018/Monte_Carlo_Integration/2D_Sampling_with_Multidimensional_Transformations>
+
+/// Uniform direction on the unit sphere.
+pub fn uniform_sphere(u: Vec2) -> Vec3 {
+    let z = 1.0 - 2.0 * u.x;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = TAU * u.y;
+    vec3(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_sphere_pdf() -> f32 {
+    FRAC_1_PI * 0.25
+}
+
+/// Uniform direction on the +z hemisphere.
+pub fn uniform_hemisphere(u: Vec2) -> Vec3 {
+    let z = u.x;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = TAU * u.y;
+    vec3(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_hemisphere_pdf() -> f32 {
+    FRAC_1_PI * 0.5
+}
+
+/// Direction on the +z hemisphere with a pdf of `cos_theta / PI`, made by projecting
+/// [`concentric_disk`] up onto the hemisphere.
+pub fn cosine_hemisphere(u: Vec2) -> Vec3 {
+    let d = concentric_disk(u);
+    let z = (1.0 - d.dot(d)).max(0.0).sqrt();
+    vec3(d.x, d.y, z)
+}
+
+pub fn cosine_hemisphere_pdf(cos_theta: f32) -> f32 {
+    cos_theta * FRAC_1_PI
+}
+
+/// Uniform point on the unit disk. Shirley and Chiu's mapping keeps the square's strata intact,
+/// unlike `sqrt(u.x)` polar mapping.
+pub fn concentric_disk(u: Vec2) -> Vec2 {
+    let o = u * 2.0 - 1.0;
+    if (o.x == 0.0) & (o.y == 0.0) {
+        return Vec2::ZERO;
+    }
+    let (r, theta) = if o.x.abs() > o.y.abs() {
+        (o.x, FRAC_PI_4 * (o.y / o.x))
+    } else {
+        (o.y, FRAC_PI_2 - FRAC_PI_4 * (o.x / o.y))
+    };
+    vec2(theta.cos(), theta.sin()) * r
+}
+
+/// Rotates `v` from the +z hemisphere to the hemisphere around the unit vector `n`.
+/// From <https://jcgt.org/published/0006/01/01/>
+pub fn tangent_to_world(v: Vec3, n: Vec3) -> Vec3 {
+    let sign = if n.z >= 0.0 { 1.0 } else { -1.0 };
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+    let t = vec3(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x);
+    let bt = vec3(b, sign + n.y * n.y * a, -n.y);
+    t * v.x + bt * v.y + n * v.z
+}
+
+/// A C style `for` loop, which rust-gpu compiles to simpler SPIR-V than a `for in` over a range.
+/// `for_!((x in a..b) { .. })` is short for `for_!((let mut x = a; x < b; x += 1) { .. })`, `step`
+/// changes the increment and `rev` visits the same values last to first, like
+/// `(a..b).step_by(s).rev()`. `continue` in the body skips the increment, so don't use it.
 #[macro_export]
 macro_rules! for_ {
     (($start:stmt; $cond:expr; $inc:expr) { $($body:tt)* }) => {{
@@ -192,4 +277,30 @@ macro_rules! for_ {
             }
         }
     }};
+    (($x:ident in $range:expr) { $($body:tt)* }) => {
+        $crate::for_!(($x in $range; step 1) { $($body)* })
+    };
+    (($x:ident in $range:expr; step $step:expr) { $($body:tt)* }) => {{
+        let range = $range;
+        let step = $step;
+        $crate::for_!((let mut $x = range.start; $x < range.end; $x += step) { $($body)* });
+    }};
+    (($x:ident in $range:expr; rev) { $($body:tt)* }) => {
+        $crate::for_!(($x in $range; step 1; rev) { $($body)* })
+    };
+    (($x:ident in $range:expr; step $step:expr; rev) { $($body:tt)* }) => {{
+        let range = $range;
+        let step = $step;
+        if range.start < range.end {
+            // The last value the forward loop visits
+            let mut $x = range.start + (range.end - 1 - range.start) / step * step;
+            loop {
+                $($body)*
+                if $x - range.start < step {
+                    break;
+                }
+                $x -= step;
+            }
+        }
+    }};
 }
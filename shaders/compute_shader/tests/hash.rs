@@ -0,0 +1,141 @@
+use compute_shader::{
+    glam::uvec2,
+    util::{hash_dump, hash_dump_args, hash_noise, uhash, unormf, HASH_NAMES},
+};
+
+/// `uhash` written out with `wrapping_mul`, what every shader language does for `u32`.
+fn reference(a: u32, b: u32) -> u32 {
+    let mut x = a.wrapping_mul(1597334673) ^ b.wrapping_mul(3812015801);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846ca68b);
+    x ^ (x >> 16)
+}
+
+#[test]
+fn uhash_golden_values() {
+    let golden = [
+        ((0, 0), 0x00000000),
+        ((1, 0), 0x22836e1f),
+        ((0, 1), 0xf0d50d58),
+        ((1, 1), 0x2581566b),
+        ((12345, 67890), 0xb8710508),
+        ((0x80000000, 7), 0x5860e7e9),
+    ];
+    for ((a, b), expected) in golden {
+        assert_eq!(uhash(a, b), expected, "uhash({a}, {b})");
+    }
+}
+
+#[test]
+fn unormf_golden_values() {
+    // The range is inclusive, `0xffffffff` rounds up to 2^32 as an f32
+    let golden = [
+        (0, 0x00000000),
+        (1, 0x2f800000),
+        (0x7fffffff, 0x3f000000),
+        (0xffffff00, 0x3f7fffff),
+        (0xffffffff, 0x3f800000),
+    ];
+    for (n, expected) in golden {
+        assert_eq!(unormf(n).to_bits(), expected, "unormf({n:#x})");
+    }
+}
+
+#[test]
+fn hash_noise_golden_values() {
+    let golden = [
+        ((0, 0), 0, 0x00000000),
+        ((1, 2), 3, 0x3f4e4fbe),
+        ((511, 511), 15, 0x3f7cfdc3),
+        ((1920, 1080), 60, 0x3dfaf8b1),
+    ];
+    for ((x, y), frame, expected) in golden {
+        let noise = hash_noise(uvec2(x, y), frame);
+        assert_eq!(noise.to_bits(), expected, "hash_noise(({x}, {y}), {frame})");
+    }
+}
+
+#[test]
+fn uhash_wraps_on_overflow() {
+    for i in 0..4096 {
+        let args = hash_dump_args(i);
+        assert_eq!(uhash(args.x, args.y), reference(args.x, args.y), "{i}");
+    }
+}
+
+#[test]
+fn hash_dump_writes_each_block() {
+    const COUNT: u32 = 32;
+    let mut data = vec![0; HASH_NAMES.len() * COUNT as usize];
+    hash_dump(COUNT, &mut data);
+    for i in 0..COUNT {
+        let args = hash_dump_args(i);
+        let noise = hash_noise(uvec2(args.x, i >> 4), i & 15);
+        assert_eq!(data[i as usize], uhash(args.x, args.y));
+        assert_eq!(data[(COUNT + i) as usize], noise.to_bits());
+        assert_eq!(data[(2 * COUNT + i) as usize], unormf(args.x).to_bits());
+    }
+}
+
+#[test]
+fn uhash_bits_are_balanced() {
+    const SAMPLES: u32 = 1 << 16;
+    let mut set = [0u32; 32];
+    for i in 0..SAMPLES {
+        let x = uhash(i & 255, i >> 8);
+        for (bit, count) in set.iter_mut().enumerate() {
+            *count += (x >> bit) & 1;
+        }
+    }
+    for (bit, count) in set.iter().enumerate() {
+        let fraction = *count as f64 / SAMPLES as f64;
+        assert!((fraction - 0.5).abs() < 0.01, "bit {bit} is set {fraction}");
+    }
+}
+
+#[test]
+fn hash_noise_is_uniform() {
+    const BUCKETS: usize = 256;
+    const SIDE: u32 = 256;
+    let mut histogram = [0u32; BUCKETS];
+    let mut sum = 0.0;
+    for y in 0..SIDE {
+        for x in 0..SIDE {
+            let noise = hash_noise(uvec2(x, y), 0);
+            assert!((0.0..=1.0).contains(&noise));
+            sum += noise as f64;
+            histogram[((noise * BUCKETS as f32) as usize).min(BUCKETS - 1)] += 1;
+        }
+    }
+    let samples = (SIDE * SIDE) as f64;
+    let mean = sum / samples;
+    assert!((mean - 0.5).abs() < 0.005, "mean {mean}");
+
+    // Chi-squared with 255 degrees of freedom has a standard deviation of about 22.6
+    let expected = samples / BUCKETS as f64;
+    let chi_squared: f64 = histogram
+        .iter()
+        .map(|&count| (count as f64 - expected).powi(2) / expected)
+        .sum();
+    assert!(chi_squared < 350.0, "chi squared {chi_squared}");
+}
+
+#[test]
+fn uhash_avalanches() {
+    // Flipping any input bit should flip about half the output bits
+    let mut flipped = 0;
+    let mut flips = 0;
+    for i in 0..256 {
+        let args = hash_dump_args(i);
+        let x = uhash(args.x, args.y);
+        for bit in 0..32 {
+            flipped += (x ^ uhash(args.x ^ (1 << bit), args.y)).count_ones();
+            flipped += (x ^ uhash(args.x, args.y ^ (1 << bit))).count_ones();
+            flips += 2;
+        }
+    }
+    let average = flipped as f64 / flips as f64;
+    assert!((average - 16.0).abs() < 0.5, "{average} bits flipped");
+}
@@ -0,0 +1,51 @@
+use compute_shader::{edge_cases::*, Hit};
+
+fn case(name: &str) -> &'static EdgeCase {
+    EDGE_CASES.iter().find(|case| case.name == name).unwrap()
+}
+
+/// The only cases that hit, the rest have to miss.
+const HITS: [(&str, bool); 2] = [("inside", true), ("back face", false)];
+
+fn hit(name: &str) -> Hit {
+    let case = case(name);
+    case.triangle.intersect(case.ray)
+}
+
+#[test]
+fn faces_are_told_apart() {
+    for (name, front_face) in HITS {
+        let hit = hit(name);
+        assert!(hit.is_hit(), "{name}");
+        assert_eq!((hit.t, hit.u, hit.v), (1.0, 0.25, 0.25), "{name}");
+        assert_eq!(hit.front_face, front_face, "{name}");
+    }
+}
+
+#[test]
+fn everything_else_misses_on_the_cpu() {
+    // Edges and vertices are outside, `u`, `v` and `t` have to be strictly inside their ranges.
+    // Degenerate triangles and parallel rays get an infinite `inv_det`, which makes `u`, `v` and
+    // `t` infinite, or NaN where their dot product is 0 (only `v` for "parallel above the plane").
+    // Either fails one of the range checks, like NaN and infinite rays.
+    let misses = EDGE_CASES
+        .iter()
+        .filter(|case| HITS.iter().all(|(name, _)| case.name != *name));
+    for case in misses {
+        let hit = case.triangle.intersect(case.ray);
+        assert!(!hit.is_hit(), "{}", case.name);
+        assert_eq!(hit.t, Hit::MISS.t, "{}", case.name);
+    }
+}
+
+#[test]
+fn packed_cases_give_the_same_hits() {
+    let cases: Vec<_> = EDGE_CASES.iter().flat_map(|case| case.pack()).collect();
+    let mut data = vec![0.0; EDGE_CASES.len() * HIT_LEN];
+    intersect_cases(EDGE_CASES.len() as u32, &cases, &mut data);
+    for (case, out) in EDGE_CASES.iter().zip(data.chunks(HIT_LEN)) {
+        let hit = case.triangle.intersect(case.ray);
+        let expected = [hit.t, hit.u, hit.v, hit.front_face as u32 as f32];
+        assert_eq!(out, expected, "{}", case.name);
+    }
+}
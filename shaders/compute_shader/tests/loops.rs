@@ -0,0 +1,50 @@
+use compute_shader::{compute, compute_for_in, compute_while, for_};
+
+fn visited(f: impl FnOnce(&mut Vec<u32>)) -> Vec<u32> {
+    let mut values = Vec::new();
+    f(&mut values);
+    values
+}
+
+#[test]
+fn ranges_visit_the_same_values_as_iterators() {
+    for (start, end) in [(0, 0), (0, 1), (0, 10), (3, 10), (5, 3)] {
+        for step in [1, 2, 3, 7] {
+            let range = start..end;
+            let forward = visited(|v| for_!((x in range.clone(); step step) { v.push(x); }));
+            let expected: Vec<u32> = range.clone().step_by(step as usize).collect();
+            assert_eq!(forward, expected, "{range:?} step {step}");
+
+            let reverse = visited(|v| for_!((x in range.clone(); step step; rev) { v.push(x); }));
+            let expected: Vec<u32> = expected.into_iter().rev().collect();
+            assert_eq!(reverse, expected, "{range:?} step {step} rev");
+        }
+        let range = start..end;
+        assert_eq!(
+            visited(|v| for_!((x in range.clone()) { v.push(x); })),
+            range.clone().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            visited(|v| for_!((x in range.clone(); rev) { v.push(x); })),
+            range.clone().rev().collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn reverse_stops_at_zero() {
+    let values = visited(|v| for_!((x in 0..u32::MAX; step 1 << 31; rev) { v.push(x); }));
+    assert_eq!(values, [1 << 31, 0]);
+}
+
+#[test]
+fn loop_forms_match() {
+    for size in [0, 1, 5, 8] {
+        let expected = compute(size);
+        for stats in [compute_for_in(size), compute_while(size)] {
+            assert_eq!(stats.sum.to_bits(), expected.sum.to_bits());
+            assert_eq!(stats.nearest_t.to_bits(), expected.nearest_t.to_bits());
+            assert_eq!(stats.hit_count, expected.hit_count);
+        }
+    }
+}
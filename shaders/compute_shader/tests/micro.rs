@@ -0,0 +1,41 @@
+use compute_shader::micro::*;
+
+const COUNT: u32 = 1 << 12;
+
+/// Max error of function `kind` over its domain, like `--micro` reports it.
+fn max_error(kind: u32) -> f64 {
+    let inputs: Vec<f32> = (0..COUNT).map(|i| input(kind, i, COUNT)).collect();
+    let mut output = vec![0.0; COUNT as usize];
+    run(kind, COUNT, &inputs, &mut output);
+    inputs
+        .iter()
+        .zip(&output)
+        .map(|(x, y)| {
+            let reference = reference(kind, *x);
+            (*y as f64 - reference).abs() / reference.abs().max(1.0)
+        })
+        .fold(0.0, f64::max)
+}
+
+#[test]
+fn inputs_stay_inside_the_domain() {
+    for (kind, (start, end)) in MICRO_DOMAINS.iter().enumerate() {
+        let kind = kind as u32;
+        assert!(input(kind, 0, COUNT) > *start);
+        assert!(input(kind, COUNT - 1, COUNT) < *end);
+    }
+}
+
+#[test]
+fn f32_functions_are_close_to_f64() {
+    for (kind, name) in MICRO_NAMES.iter().enumerate() {
+        let error = max_error(kind as u32);
+        let bound = if *name == "acos_approx" { 1e-2 } else { 1e-6 };
+        assert!(error < bound, "{name}: {error}");
+    }
+    let exact = ["copy", "saturate"];
+    for name in exact {
+        let kind = MICRO_NAMES.iter().position(|n| *n == name).unwrap();
+        assert_eq!(max_error(kind as u32), 0.0, "{name}");
+    }
+}
@@ -0,0 +1,27 @@
+use compute_shader::{compute, compute_kahan, compute_sum_f64, KahanSum};
+
+#[test]
+fn kahan_keeps_the_low_bits() {
+    let mut kahan = KahanSum::default();
+    let mut naive = 0.0_f32;
+    for _ in 0..1 << 20 {
+        kahan.add(0.1);
+        naive += 0.1;
+    }
+    let truth = (1 << 20) as f64 * 0.1_f32 as f64;
+    assert!((kahan.sum as f64 - truth).abs() <= truth * 1e-7);
+    assert!((naive as f64 - truth).abs() > truth * 1e-4);
+}
+
+#[test]
+fn kahan_is_closer_to_f64() {
+    let size = 256;
+    let truth = compute_sum_f64(size);
+    let naive = compute(size);
+    let kahan = compute_kahan(size);
+    let error = |sum: f32| (sum as f64 - truth).abs();
+    assert!(error(kahan.sum) <= error(naive.sum));
+    assert!(error(kahan.sum) <= truth.abs() * 1e-6);
+    assert_eq!(kahan.nearest_t, naive.nearest_t);
+    assert_eq!(kahan.hit_count, naive.hit_count);
+}
@@ -0,0 +1,118 @@
+use compute_shader::{
+    glam::uvec2,
+    rng::*,
+    util::{hash_noise, uhash},
+};
+
+const COORDS: [(u32, u32); 4] = [(0, 0), (1, 0), (7, 3), (511, 511)];
+
+fn take(rng: &mut impl Rng, n: usize) -> Vec<u32> {
+    (0..n).map(|_| rng.next_u32()).collect()
+}
+
+#[test]
+fn hash_rng_matches_hash_noise() {
+    for (x, y) in COORDS {
+        let coord = uvec2(x, y);
+        let mut rng = HashRng::new(coord, 9);
+        for frame in 9..24 {
+            assert_eq!(rng.next_f32(), hash_noise(coord, frame));
+        }
+    }
+}
+
+#[test]
+fn any_rng_matches_each_generator() {
+    for (x, y) in COORDS {
+        let coord = uvec2(x, y);
+        for seed in [0, 9] {
+            let expected = [
+                take(&mut HashRng::new(coord, seed), 32),
+                take(&mut Pcg32::new(coord, seed), 32),
+                take(&mut Xorshift32::new(coord, seed), 32),
+                take(&mut Sobol::new(coord, seed), 32),
+                take(&mut R2::new(coord, seed), 32),
+            ];
+            for (kind, expected) in expected.iter().enumerate() {
+                let got = take(&mut AnyRng::new(kind as u32, coord, seed), 32);
+                assert_eq!(&got, expected, "{}", RNG_NAMES[kind]);
+            }
+        }
+    }
+}
+
+#[test]
+fn compute_rng_hash_matches_compute() {
+    assert_eq!(
+        compute_shader::compute_rng(64, RNG_HASH, false),
+        compute_shader::compute(64)
+    );
+}
+
+/// The shaders are checked against these same functions with `--rng-parity`, so pinning the CPU
+/// bits here pins them everywhere.
+#[test]
+fn golden_values() {
+    let coord = uvec2(7, 3);
+    let golden: [[u32; 4]; 5] = [
+        [217911737, 308613943, 3179894332, 4286018384],
+        [1257925753, 3870189804, 4075703708, 3788352953],
+        [2005319004, 2776679434, 490376138, 3292418187],
+        [1854966521, 1227991675, 4012218653, 2945334532],
+        [3881696507, 355281138, 165767962, 934319889],
+    ];
+    for (kind, golden) in golden.iter().enumerate() {
+        let got = take(&mut AnyRng::new(kind as u32, coord, 0), 4);
+        assert_eq!(&got[..], &golden[..], "{}", RNG_NAMES[kind]);
+    }
+}
+
+#[test]
+fn sobol_dimensions_are_stratified() {
+    // Without scrambling the first 2^k points of each dimension hit every 1/2^k interval once,
+    // the nested uniform scrambles keep that property.
+    for dimension in 0..8 {
+        let mut seen = [false; 64];
+        for index in 0..64 {
+            let x = sobol(index, dimension, 1234);
+            seen[(x >> 26) as usize] = true;
+        }
+        assert!(seen.iter().all(|s| *s), "dimension {dimension}");
+    }
+}
+
+#[test]
+fn generators_are_roughly_uniform() {
+    for (kind, name) in RNG_NAMES.iter().enumerate() {
+        let mut buckets = [0u32; 16];
+        let mut n = 0;
+        for x in 0..64 {
+            for y in 0..64 {
+                let mut rng = AnyRng::new(kind as u32, uvec2(x, y), 0);
+                for _ in 0..4 {
+                    buckets[(rng.next_f32() * 16.0).min(15.0) as usize] += 1;
+                    n += 1;
+                }
+            }
+        }
+        let expected = n as f32 / 16.0;
+        for count in buckets {
+            assert!(
+                (count as f32 - expected).abs() < expected * 0.1,
+                "{name}: {buckets:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn pcg32_and_xorshift_step_like_the_reference() {
+    let mut state = 1;
+    assert_eq!(xorshift32(&mut state), 270369);
+    assert_eq!(state, 270369);
+
+    let seed = uhash(7, 3 << 11);
+    let mut state = seed;
+    pcg32(&mut state);
+    assert_eq!(state, seed.wrapping_mul(747796405).wrapping_add(2891336453));
+}
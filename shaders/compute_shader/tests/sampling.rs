@@ -0,0 +1,200 @@
+use compute_shader::{
+    glam::{uvec2, vec2, vec3, Vec2, Vec3},
+    rng::{HashRng, Rng},
+    util::*,
+};
+
+const N: u32 = 1 << 16;
+
+fn samples<T>(f: impl Fn(Vec2) -> T) -> Vec<T> {
+    let mut rng = HashRng::new(uvec2(3, 5), 0);
+    (0..N)
+        .map(|_| {
+            let u = rng.next_f32();
+            let v = rng.next_f32();
+            f(vec2(u, v))
+        })
+        .collect()
+}
+
+fn mean<T>(samples: &[T], f: impl Fn(&T) -> f32) -> f32 {
+    samples.iter().map(|s| f(s) as f64).sum::<f64>() as f32 / samples.len() as f32
+}
+
+fn assert_close(got: f32, expected: f32, what: &str) {
+    assert!(
+        (got - expected).abs() < 0.01,
+        "{what}: got {got}, expected {expected}"
+    );
+}
+
+/// Counts how many samples land in each of the 8 octants, each should get 1/8 of them.
+fn assert_octants_uniform(dirs: &[Vec3]) {
+    let mut octants = [0u32; 8];
+    for d in dirs {
+        octants
+            [(d.x > 0.0) as usize | ((d.y > 0.0) as usize) << 1 | ((d.z > 0.0) as usize) << 2] += 1;
+    }
+    let expected = dirs.len() as f32 / 8.0;
+    for count in octants {
+        assert!(
+            (count as f32 - expected).abs() < expected * 0.05,
+            "{octants:?}"
+        );
+    }
+}
+
+#[test]
+fn uniform_sphere_is_uniform() {
+    let dirs = samples(uniform_sphere);
+    for d in &dirs {
+        assert_close(d.length(), 1.0, "length");
+    }
+    assert_octants_uniform(&dirs);
+    assert_close(mean(&dirs, |d| d.x), 0.0, "mean x");
+    assert_close(mean(&dirs, |d| d.y), 0.0, "mean y");
+    assert_close(mean(&dirs, |d| d.z), 0.0, "mean z");
+    // Each axis of a uniform direction has E[x^2] = 1/3.
+    assert_close(mean(&dirs, |d| d.x * d.x), 1.0 / 3.0, "mean x^2");
+    assert_close(mean(&dirs, |d| d.z * d.z), 1.0 / 3.0, "mean z^2");
+}
+
+#[test]
+fn cube_directions_are_biased() {
+    // What `Ray::random` does, the directions bunch up toward the cube's corners and away from its
+    // axes.
+    let mut rng = HashRng::new(uvec2(3, 5), 0);
+    let dirs = (0..N)
+        .map(|_| {
+            let x = rng.next_f32();
+            let y = rng.next_f32();
+            let z = rng.next_f32();
+            (vec3(x, y, z) * 2.0 - 1.0).normalize()
+        })
+        .collect::<Vec<_>>();
+    // On the sphere P(|x| > 0.9) = 0.1.
+    let near_axis = mean(&dirs, |d| (d.x.abs() > 0.9) as u32 as f32);
+    assert!(near_axis < 0.08, "{near_axis}");
+    let near_axis = mean(&samples(uniform_sphere), |d| {
+        (d.x.abs() > 0.9) as u32 as f32
+    });
+    assert_close(near_axis, 0.1, "uniform sphere");
+}
+
+#[test]
+fn uniform_hemisphere_is_uniform() {
+    let dirs = samples(uniform_hemisphere);
+    for d in &dirs {
+        assert_close(d.length(), 1.0, "length");
+        assert!(d.z >= 0.0);
+    }
+    // P(cos_theta > c) = 1 - c
+    for c in [0.25, 0.5, 0.75] {
+        assert_close(
+            mean(&dirs, |d| (d.z > c) as u32 as f32),
+            1.0 - c,
+            "P(z > c)",
+        );
+    }
+    assert_close(mean(&dirs, |d| d.z), 0.5, "mean z");
+    assert_close(mean(&dirs, |d| d.x), 0.0, "mean x");
+    assert_close(mean(&dirs, |d| d.y), 0.0, "mean y");
+}
+
+#[test]
+fn cosine_hemisphere_is_cosine_weighted() {
+    let dirs = samples(cosine_hemisphere);
+    for d in &dirs {
+        assert_close(d.length(), 1.0, "length");
+        assert!(d.z >= 0.0);
+    }
+    // P(cos_theta > c) = 1 - c^2
+    for c in [0.25, 0.5, 0.75] {
+        assert_close(
+            mean(&dirs, |d| (d.z > c) as u32 as f32),
+            1.0 - c * c,
+            "P(z > c)",
+        );
+    }
+    assert_close(mean(&dirs, |d| d.z), 2.0 / 3.0, "mean z");
+    assert_close(mean(&dirs, |d| d.x), 0.0, "mean x");
+    assert_close(mean(&dirs, |d| d.y), 0.0, "mean y");
+}
+
+/// Integral of `pdf` over the sphere with the midpoint rule in spherical coordinates, where the
+/// solid angle of each cell is `sin(theta) dtheta dphi`.
+fn integrate(pdf: impl Fn(Vec3) -> f32) -> f32 {
+    const THETA_STEPS: u32 = 256;
+    const PHI_STEPS: u32 = 512;
+    let d_theta = std::f64::consts::PI / THETA_STEPS as f64;
+    let d_phi = std::f64::consts::TAU / PHI_STEPS as f64;
+    let mut sum = 0.0;
+    for i in 0..THETA_STEPS {
+        let theta = (i as f64 + 0.5) * d_theta;
+        for j in 0..PHI_STEPS {
+            let phi = (j as f64 + 0.5) * d_phi;
+            let d = vec3(
+                (theta.sin() * phi.cos()) as f32,
+                (theta.sin() * phi.sin()) as f32,
+                theta.cos() as f32,
+            );
+            sum += pdf(d) as f64 * theta.sin() * d_theta * d_phi;
+        }
+    }
+    sum as f32
+}
+
+#[test]
+fn pdfs_integrate_to_one() {
+    let sphere = integrate(|_| uniform_sphere_pdf());
+    let hemisphere = integrate(|d| (d.z > 0.0) as u32 as f32 * uniform_hemisphere_pdf());
+    let cosine = integrate(|d| cosine_hemisphere_pdf(d.z.max(0.0)));
+    assert_close(sphere, 1.0, "sphere");
+    assert_close(hemisphere, 1.0, "hemisphere");
+    assert_close(cosine, 1.0, "cosine hemisphere");
+}
+
+#[test]
+fn concentric_disk_is_uniform() {
+    let points = samples(concentric_disk);
+    for p in &points {
+        assert!(p.length() <= 1.0 + 1e-6, "{p}");
+    }
+    // Uniform in area, P(r < c) = c^2
+    for c in [0.25, 0.5, 0.75] {
+        assert_close(
+            mean(&points, |p| (p.length() < c) as u32 as f32),
+            c * c,
+            "P(r < c)",
+        );
+    }
+    assert_close(mean(&points, |p| p.x), 0.0, "mean x");
+    assert_close(mean(&points, |p| p.y), 0.0, "mean y");
+    assert_eq!(concentric_disk(Vec2::splat(0.5)), Vec2::ZERO);
+}
+
+#[test]
+fn concentric_disk_keeps_the_square_edges() {
+    // The edges of the square map to the unit circle.
+    for i in 0..=16 {
+        let t = i as f32 / 16.0;
+        for u in [vec2(t, 0.0), vec2(t, 1.0), vec2(0.0, t), vec2(1.0, t)] {
+            assert_close(concentric_disk(u).length(), 1.0, "edge length");
+        }
+    }
+}
+
+#[test]
+fn tangent_to_world_is_orthonormal() {
+    let normals = samples(uniform_sphere);
+    for n in normals.iter().step_by(64).chain(&[Vec3::Z, -Vec3::Z]) {
+        let x = tangent_to_world(Vec3::X, *n);
+        let y = tangent_to_world(Vec3::Y, *n);
+        assert!((tangent_to_world(Vec3::Z, *n) - *n).length() < 1e-5);
+        assert!(x.dot(*n).abs() < 1e-5, "{n}");
+        assert!(y.dot(*n).abs() < 1e-5, "{n}");
+        assert!(x.dot(y).abs() < 1e-5, "{n}");
+        assert_close(x.length(), 1.0, "x length");
+        assert_close(y.length(), 1.0, "y length");
+    }
+}
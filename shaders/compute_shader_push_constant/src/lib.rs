@@ -0,0 +1,18 @@
+#![cfg_attr(target_arch = "spirv", no_std)]
+//! `main` with `settings` from push constants, for `--settings-variants`. It's its own crate so
+//! the module of `compute_shader` doesn't declare push constants, which naga rejects on adapters
+//! without them whichever entry point runs.
+
+use compute_shader::{compute, glam::*};
+use spirv_std::spirv;
+
+// LocalSize/numthreads of (x = 1, y = 1, z = 1)
+#[spirv(compute(threads(1)))]
+pub fn main_push_constant(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] data: &mut [f32],
+    #[spirv(push_constant)] settings: &UVec4,
+) {
+    let index = id.x as usize;
+    compute(settings.x).write(data, index);
+}
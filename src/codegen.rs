@@ -0,0 +1,152 @@
+//! Generates the GLSL and Slang kernels from `compute_shader.wgsl` with naga's GLSL and HLSL
+//! backends.
+//!
+//! GLSL has one entry point per module, so [`glsl`] translates the WGSL for each entry point when
+//! the kernel is loaded. Slang reads HLSL, [`slang`] only moves the resources from HLSL registers
+//! to Vulkan bindings, and the result is committed as `compute_shader.slang` for build.rs. naga
+//! writes push constants for D3D and OpenGL, both backends' are turned back into Vulkan push
+//! constant blocks.
+
+use crate::roundtrip;
+use naga::back::{glsl as glsl_out, hlsl};
+use std::{borrow::Cow, path::PathBuf};
+use wgpu::ShaderSource;
+
+const HEADER: &str =
+    "// Generated from compute_shader.wgsl, edit that and run with `--generate-shaders`.\n\n";
+
+fn parse(wgsl: &str) -> Result<(naga::Module, naga::valid::ModuleInfo), String> {
+    let module = roundtrip::parse(&ShaderSource::Wgsl(Cow::Borrowed(wgsl)))?;
+    let info = roundtrip::validate(&module)?;
+    Ok((module, info))
+}
+
+/// `entry_point` of `wgsl` in GLSL, where it is called `main`.
+pub fn glsl(wgsl: &str, entry_point: &str) -> Result<String, String> {
+    let (module, info) = parse(wgsl)?;
+    // naga's GLSL frontend wants every block bound. The backend only writes the binding, which
+    // the frontend reads back in group 0.
+    let mut binding_map = glsl_out::BindingMap::new();
+    for (_, global) in module.global_variables.iter() {
+        if let Some(binding) = &global.binding {
+            if binding.group != 0 {
+                return Err(format!("{:?} isn't in group 0", global.name));
+            }
+            binding_map.insert(binding.clone(), binding.binding as u8);
+        }
+    }
+    let options = glsl_out::Options {
+        version: glsl_out::Version::Desktop(450),
+        writer_flags: glsl_out::WriterFlags::empty(),
+        binding_map,
+        zero_initialize_workgroup_memory: false,
+    };
+    let pipeline_options = glsl_out::PipelineOptions {
+        shader_stage: naga::ShaderStage::Compute,
+        entry_point: entry_point.to_string(),
+        multiview: None,
+    };
+    let mut out = String::new();
+    glsl_out::Writer::new(
+        &mut out,
+        &module,
+        &info,
+        &options,
+        &pipeline_options,
+        naga::proc::BoundsCheckPolicies::default(),
+    )
+    .and_then(|mut writer| writer.write())
+    .map_err(|err| format!("naga couldn't write glsl for {entry_point}: {err}"))?;
+
+    let mut glsl = String::new();
+    for line in out.lines() {
+        // `uniform T name;`, only push constants are written without a layout. The block keeps
+        // `name` readable as is.
+        match line.strip_prefix("uniform ") {
+            Some(global) => {
+                let name = global.trim_end_matches(';').rsplit(' ').next().unwrap();
+                glsl.push_str(&format!(
+                    "layout(push_constant) uniform {name}_block {{ {global} }};"
+                ));
+            }
+            None => glsl.push_str(line),
+        }
+        glsl.push('\n');
+    }
+    Ok(glsl)
+}
+
+/// Every entry point of `wgsl` in Slang.
+pub fn slang(wgsl: &str) -> Result<String, String> {
+    let (module, info) = parse(wgsl)?;
+    let options = hlsl::Options {
+        // Any register will do, the block is rewritten below
+        push_constants_target: Some(hlsl::BindTarget::default()),
+        zero_initialize_workgroup_memory: false,
+        ..Default::default()
+    };
+    let mut out = String::new();
+    hlsl::Writer::new(&mut out, &options)
+        .write(&module, &info)
+        .map_err(|err| format!("naga couldn't write hlsl: {err}"))?;
+
+    let mut slang = HEADER.to_string();
+    for (number, line) in out.lines().enumerate() {
+        if line.contains("register(") {
+            let global = slang_global(line).map_err(|err| format!("line {}: {err}", number + 1))?;
+            slang.push_str(&global);
+        } else {
+            slang.push_str(line);
+        }
+        slang.push('\n');
+    }
+    Ok(slang)
+}
+
+/// A global naga wrote with an HLSL register, with a Vulkan binding instead:
+/// - `RWByteAddressBuffer data : register(u0);`
+/// - `cbuffer settings : register(b1) { uint4 settings; }`
+/// - `ConstantBuffer<uint4> push_settings: register(b0);`, the push constants
+fn slang_global(line: &str) -> Result<String, String> {
+    let (declaration, rest) = line.split_once("register(").unwrap();
+    let declaration = declaration.trim_end().trim_end_matches(':').trim_end();
+    let (register, members) = rest
+        .split_once(')')
+        .ok_or_else(|| format!("unterminated register in {line:?}"))?;
+    let (register, space) = register.split_once(", space").unwrap_or((register, "0"));
+    let binding: u32 = register
+        .get(1..)
+        .and_then(|index| index.parse().ok())
+        .ok_or_else(|| format!("unexpected register {register:?}"))?;
+    let members = members.trim_start_matches(';').trim();
+
+    if let Some(declaration) = declaration.strip_prefix("ConstantBuffer<") {
+        let (ty, name) = declaration
+            .split_once("> ")
+            .ok_or_else(|| format!("unexpected push constants {line:?}"))?;
+        return Ok(format!(
+            "[[vk::push_constant]] cbuffer {name}_block {{ {ty} {name}; }}"
+        ));
+    }
+    let attribute = format!("[[vk::binding({binding}, {space})]]");
+    match declaration.strip_prefix("cbuffer ") {
+        // The block is renamed so it doesn't clash with its member
+        Some(name) => Ok(format!("{attribute} cbuffer {name}_block {members}")),
+        None if members.is_empty() => Ok(format!("{attribute} {declaration};")),
+        None => Err(format!("unexpected global {line:?}")),
+    }
+}
+
+/// The generated files and what they should contain.
+pub fn outputs() -> [(PathBuf, String); 1] {
+    let path = |name| [env!("CARGO_MANIFEST_DIR"), "src", name].iter().collect();
+    let slang = slang(include_str!("compute_shader.wgsl")).unwrap_or_else(|err| panic!("{err}"));
+    [(path("compute_shader.slang"), slang)]
+}
+
+pub fn write() {
+    for (path, source) in outputs() {
+        std::fs::write(&path, source).expect("unable to write shader");
+        println!("wrote {}", path.display());
+    }
+}
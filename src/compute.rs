@@ -1,10 +1,28 @@
-use crate::{maybe_watch, timestamp::Timestamp, Options};
+use crate::{
+    codegen, external, interpreter, load_rust_gpu, load_spv_module, ppm, reflect, roundtrip,
+    rust_gpu::{self, RustGpuConfig},
+    scene,
+    settings::{self, Settings},
+    slang::{self, SlangCompiler},
+    slang_spv_path, spirv_dump,
+    timestamp::Timestamp,
+    Options,
+};
+
+use compute_shader::{
+    bvh::Bvh,
+    edge_cases::{self, EDGE_CASES, HIT_LEN},
+    micro::{self, MICRO_NAMES},
+    rng::{self, RNG_NAMES},
+    util::{hash_dump, HASH_NAMES},
+    Stats,
+};
 
 use std::{
+    borrow::Cow,
+    collections::BTreeMap,
     convert::TryInto,
-    io::Read,
     path::{Path, PathBuf},
-    process::Command,
     time::{Duration, Instant},
 };
 use wgpu::{
@@ -16,98 +34,1051 @@ use wgpu::{
 };
 
 fn load_shader_module(path: &Path) -> Vec<u8> {
-    let mut f = std::fs::File::open(&path).expect("no file found");
-    let metadata = std::fs::metadata(&path).expect("unable to read metadata");
-    let mut buffer = vec![0; metadata.len() as usize];
-    f.read(&mut buffer).expect("buffer overflow");
-    buffer
+    std::fs::read(path).unwrap_or_else(|err| panic!("unable to read {path:?}: {err}"))
 }
 
-pub fn print_if_not_eq(a: f32, b: f32) {
+/// The WGSL kernel, which the GLSL and Slang ones are generated from, see [`codegen`].
+const WGSL: &str = include_str!("compute_shader.wgsl");
+
+/// Why a Slang module isn't there.
+const SLANG_MISSING: &str = "slangc wasn't found when building (set SLANGC or use --compile-slang)";
+
+/// Entry points in `compute_shader.slang`, each one is compiled to its own SPIR-V module.
+fn slang_entry_points() -> Vec<&'static str> {
+    slang::entry_points(include_str!("compute_shader.slang"))
+}
+
+/// `entry_point` of `wgsl` translated to GLSL, where it is always called `main`.
+fn glsl_module(wgsl: &str, entry_point: &str) -> ShaderModuleDescriptor<'static> {
+    let glsl = codegen::glsl(wgsl, entry_point).unwrap_or_else(|err| panic!("{err}"));
+    ShaderModuleDescriptor {
+        label: None,
+        source: ShaderSource::Glsl {
+            shader: Cow::Owned(glsl),
+            stage: naga::ShaderStage::Compute,
+            defines: Default::default(),
+        },
+    }
+}
+
+fn slang_source_path() -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "src", "compute_shader.slang"]
+        .iter()
+        .copied()
+        .collect::<PathBuf>()
+}
+
+/// Where `entry_point` of the Slang kernel is loaded from, the module `--compile-slang` compiled
+/// into [`slang_cache_dir`] or else the one build.rs compiled.
+fn slang_module_path(options: &Options, entry_point: &str) -> PathBuf {
+    if options.compile_slang {
+        slang_compiler(options).cache_path(&slang_source_path(), entry_point, &slang_cache_dir())
+    } else {
+        slang_spv_path(entry_point)
+    }
+}
+
+/// With `--compile-slang`, compiles every entry point of the Slang kernel into
+/// [`slang_cache_dir`], otherwise checks that build.rs compiled the current source.
+fn prepare_slang(options: &Options) {
+    if !options.compile_slang {
+        check_slang_fresh(options);
+        return;
+    }
+    let compiler = slang_compiler(options);
+    for entry_point in slang_entry_points() {
+        compiler
+            .compile_cached(&slang_source_path(), entry_point, &slang_cache_dir())
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+}
+
+/// Warns about Slang SPIR-V that wasn't compiled from the current `compute_shader.slang`, or
+/// panics with `--require-fresh`.
+fn check_slang_fresh(options: &Options) {
+    let source = slang_source_path();
+    let stale: Vec<_> = slang_entry_points()
+        .into_iter()
+        .map(slang_spv_path)
+        .filter(|spv| spv.exists() && !slang::is_fresh(&source, spv))
+        .collect();
+    if stale.is_empty() {
+        return;
+    }
+    let message = format!(
+        "{stale:?} wasn't compiled from the current compute_shader.slang, re-run `cargo build` with \
+        slangc on the PATH or in SLANGC"
+    );
+    if options.require_fresh {
+        panic!("{message}");
+    }
+    println!("warning: {message}");
+}
+
+pub fn print_if_not_eq(a: Stats, b: Stats) {
     if a != b {
-        println!("cpu != gpu: {} != {}", a, b)
+        println!("cpu != gpu: {:?} != {:?}", a, b)
     }
 }
 
+/// Everything a benchmark needs besides the shader module.
+#[derive(Clone, Copy)]
+struct Workload<'a> {
+    entry_point: &'a str,
+    /// Bound as read-only storage buffers starting at binding 2.
+    inputs: &'a [&'a [u8]],
+    /// Number of f32s in the output buffer, which starts with the [`Stats`].
+    output_len: usize,
+    /// Contents of the `settings` uniform, `x` is always the size.
+    settings: [u32; 4],
+    cpu_result: Stats,
+    /// From `--dump-shaders`, every variant passed to [`bench`] is dumped here.
+    dump_dir: Option<&'a Path>,
+    /// From `--spirv-passthrough`, [`bench`] then also times SPIR-V variants handed to the driver
+    /// without going through naga, on adapters that support it.
+    spirv_passthrough: bool,
+    /// Features of the adapter, queried once at startup.
+    adapter_features: Features,
+}
+
 pub fn start(options: &Options) {
-    let compiled_shader_modules = maybe_watch(None);
+    // Loaded first so a bad path fails before anything is benchmarked
+    let external_shaders: Vec<_> = options
+        .shaders
+        .iter()
+        .map(|path| {
+            let (shader_module, entry_point) =
+                external::load(path, &options.entry).unwrap_or_else(|err| panic!("{err}"));
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            (name, shader_module, entry_point)
+        })
+        .collect();
+
+    if let Some(dir) = &options.dump_shaders {
+        std::fs::create_dir_all(dir).expect("unable to create the dump directory");
+    }
+    let adapter_features = futures::executor::block_on(request_adapter()).1.features();
+    if options.spirv_passthrough && !adapter_features.contains(Features::SPIRV_SHADER_PASSTHROUGH) {
+        println!("SPIR-V passthrough isn't supported by this adapter, only timing the naga path");
+    }
+
+    let compiled_shader_modules =
+        load_rust_gpu(&RustGpuConfig::default(), options.compile_rust_gpu);
+    let rust_gpu = &compiled_shader_modules.named_spv_modules[0].1;
 
     let start = Instant::now();
     let cpu_result = compute_shader::compute(options.size);
     let took = start.elapsed();
     println!("CPU Took:\t{took:?}");
 
-    let (gpu_duration, _gpu_result) = futures::executor::block_on(start_internal(
-        options,
-        compiled_shader_modules.named_spv_modules[0].1.clone(),
-    ));
+    let workload = Workload {
+        entry_point: "main",
+        inputs: &[],
+        output_len: Stats::LEN,
+        settings: [options.size; 4],
+        cpu_result,
+        dump_dir: options.dump_shaders.as_deref(),
+        spirv_passthrough: options.spirv_passthrough,
+        adapter_features,
+    };
+
+    let (gpu_duration, _gpu_result) =
+        futures::executor::block_on(start_internal(rust_gpu.clone(), &workload, false));
     println!("rust-gpu warm up Took:\t{:?}", gpu_duration);
 
-    let (gpu_duration, gpu_result) = futures::executor::block_on(start_internal(
-        options,
-        compiled_shader_modules.named_spv_modules[0].1.clone(),
-    ));
-    println!("rust-gpu Took:\t{:?}", gpu_duration);
-    print_if_not_eq(cpu_result, gpu_result);
+    bench("rust-gpu", rust_gpu.clone(), &workload);
 
-    let (gpu_duration, gpu_result) = futures::executor::block_on(start_internal(
-        options,
-        include_wgsl!("compute_shader.wgsl"),
-    ));
-    println!("wgsl Took:\t{:?}", gpu_duration);
-    print_if_not_eq(cpu_result, gpu_result);
+    if options.rust_gpu_matrix {
+        start_rust_gpu_matrix(&workload);
+    }
+
+    if options.loop_forms {
+        start_loop_forms(options, rust_gpu, &workload);
+    }
+
+    bench("wgsl", include_wgsl!("compute_shader.wgsl"), &workload);
+
+    bench("glsl", glsl_module(WGSL, "main"), &workload);
+
+    let src_path = slang_source_path();
+
+    prepare_slang(options);
+
+    let dst_path = slang_module_path(options, "main");
+    if dst_path.exists() {
+        let dst_string = dst_path.to_string_lossy().to_string();
+        let slang_spv = load_shader_module(&dst_path);
+        bench(
+            "slang",
+            ShaderModuleDescriptor {
+                label: Some(&dst_string),
+                source: util::make_spirv(&slang_spv),
+            },
+            &workload,
+        );
+    } else {
+        println!("slang skipped, {SLANG_MISSING}");
+    }
+
+    if options.naga_roundtrip {
+        bench_roundtrip(&shader_variants(options, rust_gpu, "main"), "", &workload);
+    }
+
+    if options.settings_variants {
+        bench_settings_variants(options, rust_gpu, &workload);
+    }
+
+    for (name, shader_module, entry_point) in external_shaders {
+        let workload = Workload {
+            entry_point: &entry_point,
+            ..workload
+        };
+        catch_failure(&name, || bench(&name, shader_module, &workload));
+    }
+
+    if options.slang_matrix {
+        start_slang_matrix(options, &src_path, &workload);
+    }
+
+    if options.precision {
+        start_precision(options, rust_gpu, &workload);
+    }
+
+    if options.micro {
+        start_micro(options, rust_gpu, &workload);
+    }
 
-    let src_path = [env!("CARGO_MANIFEST_DIR"), "src", "compute_shader.slang"]
+    if options.edge_cases {
+        start_edge_cases(options, rust_gpu, &workload);
+    }
+
+    if options.bvh || options.camera {
+        start_bvh(options, rust_gpu, &workload);
+    }
+
+    if let Some(rng_kind) = options.rng {
+        start_rng(options, rust_gpu, &workload, rng_kind);
+    }
+
+    if options.rng_parity {
+        rng_parity(options, rust_gpu, &workload);
+    }
+}
+
+/// Variants that fail to build or that wgpu rejects, like the debug printf panic strategy on
+/// drivers without the extension, are reported and skipped.
+fn start_rust_gpu_matrix(workload: &Workload) {
+    for config in rust_gpu::matrix() {
+        let shader_module = match config.build_cached() {
+            Ok(path) => load_spv_module(&path),
+            Err(err) => {
+                println!("{} skipped, build failed: {err}", config.name);
+                continue;
+            }
+        };
+        catch_failure(&config.name, || {
+            bench(&config.name, shader_module, workload)
+        });
+    }
+}
+
+/// (form, entry point) of [`compute_shader::compute`] with its loops written each way. `for` is
+/// `for_!` in Rust. Only Rust has `for in` loops, the other forms run in every language.
+const LOOP_FORMS: [(&str, &str); 3] = [
+    ("for", "main"),
+    ("for in", "main_for_in"),
+    ("while", "main_while"),
+];
+
+/// The SPIR-V each loop form compiles to and how long it takes. The entry points share one
+/// module, so only the functions each one calls are counted.
+fn start_loop_forms(
+    options: &Options,
+    rust_gpu: &ShaderModuleDescriptor<'static>,
+    workload: &Workload,
+) {
+    for (form, entry_point) in LOOP_FORMS {
+        let variants = if entry_point == "main_for_in" {
+            vec![("rust-gpu".to_string(), rust_gpu.clone(), entry_point)]
+        } else {
+            shader_variants(options, rust_gpu, entry_point)
+        };
+        for (name, shader_module, entry_point) in variants {
+            let name = format!("{name} {form}");
+            let words = spirv_dump::spirv_words(&shader_module.source);
+            let stats = spirv_dump::SpirvStats::for_entry_point(&words, entry_point)
+                .unwrap_or_else(|| panic!("{name} has no {entry_point} entry point"));
+            println!("{name} SPIR-V:\t{}", stats.summary());
+            let workload = Workload {
+                entry_point,
+                ..*workload
+            };
+            catch_failure(&name, || bench(&name, shader_module, &workload));
+        }
+    }
+}
+
+/// Runs `f`, reporting a panic, like wgpu rejecting the module, instead of ending the whole run.
+fn catch_failure<T>(name: &str, f: impl FnOnce() -> T) -> Option<T> {
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+    if result.is_err() {
+        println!("{name} failed to run");
+    }
+    result.ok()
+}
+
+fn slang_compiler(options: &Options) -> SlangCompiler {
+    SlangCompiler {
+        binary: options.slangc.clone(),
+        profile: options.slang_profile.clone(),
+        opt_level: options.slang_opt,
+        emit: options.slang_emit,
+        extra_flags: options.slang_flags.clone(),
+    }
+}
+
+fn slang_cache_dir() -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "target", "slang-cache"]
         .iter()
-        .copied()
-        .collect::<PathBuf>();
-
-    let dst_path = [
-        env!("CARGO_MANIFEST_DIR"),
-        "src",
-        "compute_shader_slang.spv",
-    ]
-    .iter()
-    .copied()
-    .collect::<PathBuf>();
-    let dst_string = dst_path.to_string_lossy().to_string();
+        .collect()
+}
 
-    if options.compile_slang {
-        let out = Command::new("slangc")
-            .arg(src_path.to_string_lossy().to_string())
-            //.arg("-O3")
-            .arg("-profile")
-            .arg("sm_5_0")
-            .arg("-stage")
-            .arg("compute")
-            .arg("-entry")
-            .arg("main")
-            .arg("-o")
-            .arg(dst_string.clone())
-            .output()
-            .expect("failed to execute process");
-        if out.stderr.len() > 1 {
-            println!("slangc stderr: {}", String::from_utf8_lossy(&out.stderr));
+fn start_slang_matrix(options: &Options, src_path: &Path, workload: &Workload) {
+    let cache_dir = slang_cache_dir();
+    for compiler in slang::matrix(&slang_compiler(options)) {
+        let name = format!("slang {}", compiler.name());
+        let path = match compiler.compile_cached(src_path, workload.entry_point, &cache_dir) {
+            Ok(path) => path,
+            Err(err) => {
+                println!("{name} skipped, {err}");
+                continue;
+            }
+        };
+        let spv = load_shader_module(&path);
+        bench(
+            &name,
+            ShaderModuleDescriptor {
+                label: None,
+                source: util::make_spirv(&spv),
+            },
+            workload,
+        );
+    }
+}
+
+fn start_bvh(options: &Options, rust_gpu: &ShaderModuleDescriptor<'static>, workload: &Workload) {
+    let triangles = match &options.obj {
+        Some(path) => {
+            scene::load_obj(path).unwrap_or_else(|err| panic!("unable to load {path:?}: {err}"))
+        }
+        None => scene::random_triangles(options.triangles),
+    };
+
+    let start = Instant::now();
+    let bvh = Bvh::build(&triangles);
+    let took = start.elapsed();
+    println!(
+        "BVH build Took:\t{took:?} ({} triangles, {} nodes)",
+        triangles.len(),
+        bvh.nodes.len()
+    );
+
+    let triangle_data = bvh
+        .triangles
+        .iter()
+        .map(|v| v.to_array())
+        .collect::<Vec<_>>();
+    let node_data = bvh
+        .nodes
+        .iter()
+        .map(|n| [n.min.to_array(), n.max.to_array()])
+        .collect::<Vec<_>>();
+    let inputs: [&[u8]; 2] = [
+        bytemuck::cast_slice(&triangle_data),
+        bytemuck::cast_slice(&node_data),
+    ];
+
+    if options.bvh {
+        let start = Instant::now();
+        let cpu_result = compute_shader::compute_bvh(options.size, &bvh.nodes, &bvh.triangles);
+        let took = start.elapsed();
+        println!("CPU bvh Took:\t{took:?}");
+
+        bench_variants(
+            options,
+            "bvh",
+            rust_gpu,
+            &Workload {
+                entry_point: "main_bvh",
+                inputs: &inputs,
+                output_len: Stats::LEN,
+                settings: [options.size; 4],
+                cpu_result,
+                ..*workload
+            },
+        );
+    }
+
+    if options.camera {
+        let pixels = options.size as usize * options.size as usize;
+        let mut image = vec![0.0; pixels];
+
+        let start = Instant::now();
+        let cpu_result =
+            compute_shader::compute_camera(options.size, &bvh.nodes, &bvh.triangles, &mut image, 0);
+        let took = start.elapsed();
+        println!("CPU camera Took:\t{took:?}");
+        write_camera_image(options, "cpu", &image);
+
+        let outputs = bench_variants(
+            options,
+            "camera",
+            rust_gpu,
+            &Workload {
+                entry_point: "main_camera",
+                inputs: &inputs,
+                output_len: Stats::LEN + pixels,
+                settings: [options.size; 4],
+                cpu_result,
+                ..*workload
+            },
+        );
+        for (name, output) in outputs {
+            write_camera_image(options, &name, &output[Stats::LEN..]);
         }
     }
+}
+
+fn write_camera_image(options: &Options, variant: &str, image: &[f32]) {
+    let variant = variant.replace(" -> ", "_to_");
+    let path = options.ppm_dir.join(format!("camera_{variant}.ppm"));
+    ppm::write_depth(&path, options.size, image).expect("unable to write ppm image");
+}
 
+/// A shader variant's name, module and the entry point name to use for it.
+type Variant<'a> = (String, ShaderModuleDescriptor<'static>, &'a str);
+
+/// The rust-gpu, WGSL, GLSL and (if it has been compiled) Slang versions of `entry_point`.
+fn shader_variants<'a>(
+    options: &Options,
+    rust_gpu: &ShaderModuleDescriptor<'static>,
+    entry_point: &'a str,
+) -> Vec<Variant<'a>> {
+    let mut variants = vec![
+        ("rust-gpu".to_string(), rust_gpu.clone(), entry_point),
+        (
+            "wgsl".to_string(),
+            include_wgsl!("compute_shader.wgsl"),
+            entry_point,
+        ),
+        ("glsl".to_string(), glsl_module(WGSL, entry_point), "main"),
+    ];
+
+    let dst_path = slang_module_path(options, entry_point);
+    if !dst_path.exists() {
+        println!("slang {entry_point} skipped, {SLANG_MISSING}");
+        return variants;
+    }
     let slang_spv = load_shader_module(&dst_path);
+    variants.push((
+        "slang".to_string(),
+        ShaderModuleDescriptor {
+            label: None,
+            source: ShaderSource::SpirV(Cow::Owned(util::make_spirv_raw(&slang_spv).into_owned())),
+        },
+        // slangc names the SPIR-V entry point `main` whatever it is called in the source.
+        "main",
+    ));
+    variants
+}
 
-    let (gpu_duration, gpu_result) = futures::executor::block_on(start_internal(
-        options,
+/// `variants` translated by naga into the other language, see [`roundtrip`], and whether each one
+/// is handed to the driver as is. wgpu would read naga's SPIR-V back through naga and write it out
+/// again, so it's only loaded as is with [`Features::SPIRV_SHADER_PASSTHROUGH`], and named
+/// `-> spv -> naga` without it. Translations naga can't do are reported and skipped.
+fn roundtrip_variants<'a>(
+    variants: &[Variant<'a>],
+    adapter_features: Features,
+) -> Vec<(Variant<'a>, bool)> {
+    let supported = adapter_features.contains(Features::SPIRV_SHADER_PASSTHROUGH);
+    let mut translated = Vec::new();
+    for (name, shader_module, entry_point) in variants {
+        let name = roundtrip::name(name, &shader_module.source);
+        match roundtrip::translate(&shader_module.source) {
+            Ok(source) => {
+                let spirv = matches!(source, ShaderSource::SpirV(_));
+                let name = if spirv && !supported {
+                    format!("{name} -> naga")
+                } else {
+                    name
+                };
+                let shader_module = ShaderModuleDescriptor {
+                    label: None,
+                    source,
+                };
+                translated.push(((name, shader_module, *entry_point), spirv && supported));
+            }
+            Err(err) => println!("{name} skipped, {err}"),
+        }
+    }
+    translated
+}
+
+/// `main_constant` of every language with `size` written in, see [`Settings::Constant`].
+/// Slang is compiled again for each size.
+fn constant_variants(
+    options: &Options,
+    rust_gpu: &ShaderModuleDescriptor,
+    size: u32,
+) -> Vec<Variant<'static>> {
+    let entry_point = Settings::Constant.entry_point();
+    let specialize =
+        |source| settings::specialize_source(source, size).unwrap_or_else(|err| panic!("{err}"));
+
+    let mut words = spirv_dump::spirv_words(&rust_gpu.source);
+    settings::specialize_spirv(&mut words, settings::SIZE_SPEC_ID, size)
+        .unwrap_or_else(|err| panic!("rust-gpu: {err}"));
+    let wgsl = specialize(WGSL);
+    let mut variants = vec![
+        (
+            "rust-gpu".to_string(),
+            ShaderModuleDescriptor {
+                label: None,
+                source: ShaderSource::SpirV(Cow::Owned(words)),
+            },
+            entry_point,
+        ),
+        (
+            "wgsl".to_string(),
+            ShaderModuleDescriptor {
+                label: None,
+                source: ShaderSource::Wgsl(Cow::Owned(wgsl.clone())),
+            },
+            entry_point,
+        ),
+        ("glsl".to_string(), glsl_module(&wgsl, entry_point), "main"),
+    ];
+
+    let cache_dir = slang_cache_dir();
+    std::fs::create_dir_all(&cache_dir).expect("unable to create the slang cache");
+    let src_path = cache_dir.join(format!("compute_shader_size_{size}.slang"));
+    std::fs::write(&src_path, specialize(include_str!("compute_shader.slang")))
+        .unwrap_or_else(|err| panic!("unable to write {src_path:?}: {err}"));
+    match slang_compiler(options).compile_cached(&src_path, entry_point, &cache_dir) {
+        Ok(path) => variants.push((
+            "slang".to_string(),
+            load_spv_module(&path),
+            // slangc names the SPIR-V entry point `main` whatever it is called in the source.
+            "main",
+        )),
+        Err(err) => println!("slang {entry_point} skipped, {err}"),
+    }
+    variants
+}
+
+/// `main_push_constant` of every language, built on its own since the other modules don't
+/// declare push constants, see [`settings::with_push_constants`].
+fn push_constant_variants(options: &Options) -> Vec<Variant<'static>> {
+    let entry_point = Settings::PushConstant.entry_point();
+    let mut variants = Vec::new();
+    let config = RustGpuConfig {
+        shader_crate: rust_gpu::PUSH_CONSTANT_CRATE,
+        ..Default::default()
+    };
+    match config.build_cached() {
+        Ok(path) => variants.push(("rust-gpu".to_string(), load_spv_module(&path), entry_point)),
+        Err(err) => println!("rust-gpu {entry_point} skipped, build failed: {err}"),
+    }
+    let wgsl = settings::with_push_constants(WGSL);
+    variants.push((
+        "wgsl".to_string(),
         ShaderModuleDescriptor {
-            label: Some(&dst_string),
-            source: util::make_spirv(&slang_spv),
+            label: None,
+            source: ShaderSource::Wgsl(Cow::Owned(wgsl.clone())),
         },
+        entry_point,
     ));
-    println!("slang Took:\t{:?}", gpu_duration);
-    print_if_not_eq(cpu_result, gpu_result);
+    variants.push(("glsl".to_string(), glsl_module(&wgsl, entry_point), "main"));
+
+    let cache_dir = slang_cache_dir();
+    std::fs::create_dir_all(&cache_dir).expect("unable to create the slang cache");
+    let src_path = cache_dir.join("compute_shader_push_constant.slang");
+    let slang = codegen::slang(&wgsl).unwrap_or_else(|err| panic!("{err}"));
+    std::fs::write(&src_path, slang)
+        .unwrap_or_else(|err| panic!("unable to write {src_path:?}: {err}"));
+    match slang_compiler(options).compile_cached(&src_path, entry_point, &cache_dir) {
+        Ok(path) => variants.push((
+            "slang".to_string(),
+            load_spv_module(&path),
+            // slangc names the SPIR-V entry point `main` whatever it is called in the source.
+            "main",
+        )),
+        Err(err) => println!("slang {entry_point} skipped, {err}"),
+    }
+    variants
 }
 
-async fn start_internal(
+/// `main` of every language with `settings` in push constants and with the size baked in, see
+/// [`Settings`], compared to the uniform buffer of the plain runs. Push constants are skipped on
+/// adapters without them.
+fn bench_settings_variants(
     options: &Options,
+    rust_gpu: &ShaderModuleDescriptor<'static>,
+    workload: &Workload,
+) {
+    for kind in Settings::ALL {
+        let variants = match kind {
+            Settings::PushConstant
+                if !workload.adapter_features.contains(Features::PUSH_CONSTANTS) =>
+            {
+                println!(
+                    "{} skipped, push constants aren't supported by this adapter",
+                    kind.name()
+                );
+                continue;
+            }
+            Settings::PushConstant => push_constant_variants(options),
+            Settings::Constant => constant_variants(options, rust_gpu, workload.settings[0]),
+        };
+        for (name, shader_module, entry_point) in variants {
+            let name = format!("{name} {}", kind.name());
+            let workload = Workload {
+                entry_point,
+                ..*workload
+            };
+            catch_failure(&name, || bench(&name, shader_module, &workload));
+        }
+    }
+}
+
+/// Runs the workload with every shader variant, and their naga translations with
+/// `--naga-roundtrip`, returns the output buffer of each one.
+fn bench_variants(
+    options: &Options,
+    suffix: &str,
+    rust_gpu: &ShaderModuleDescriptor<'static>,
+    workload: &Workload,
+) -> Vec<(String, Vec<f32>)> {
+    let variants = shader_variants(options, rust_gpu, workload.entry_point);
+    let mut outputs: Vec<_> = variants
+        .iter()
+        .cloned()
+        .map(|(name, shader_module, entry_point)| {
+            let output = bench(
+                &format!("{name} {suffix}"),
+                shader_module,
+                &Workload {
+                    entry_point,
+                    ..*workload
+                },
+            );
+            (name, output)
+        })
+        .collect();
+    if options.naga_roundtrip {
+        outputs.extend(bench_roundtrip(&variants, suffix, workload));
+    }
+    outputs
+}
+
+/// Like [`bench_variants`] with the naga translations of `variants`, which wgpu or the driver may
+/// reject, so failures are reported and skipped.
+fn bench_roundtrip(
+    variants: &[Variant],
+    suffix: &str,
+    workload: &Workload,
+) -> Vec<(String, Vec<f32>)> {
+    let mut outputs = Vec::new();
+    for ((name, shader_module, entry_point), passthrough) in
+        roundtrip_variants(variants, workload.adapter_features)
+    {
+        let full_name = format!("{name} {suffix}").trim_end().to_string();
+        let workload = Workload {
+            entry_point,
+            ..*workload
+        };
+        if let Some(output) = catch_failure(&full_name, || {
+            if passthrough {
+                dump(&full_name, &shader_module.source, &workload);
+                run(&full_name, shader_module, &workload, true)
+            } else {
+                bench(&full_name, shader_module, &workload)
+            }
+        }) {
+            outputs.push((name, output));
+        }
+    }
+    outputs
+}
+
+/// Error of the f32 sums of `main` and `main_kahan` in every language, and on the CPU, against
+/// [`compute_shader::compute_sum_f64`]. Kahan summation takes out most of the accumulation error,
+/// what's left comes from the arithmetic.
+fn start_precision(
+    options: &Options,
+    rust_gpu: &ShaderModuleDescriptor<'static>,
+    workload: &Workload,
+) {
+    let truth = compute_shader::compute_sum_f64(options.size);
+    println!("f64 sum:\t{truth}");
+    let print_error = |name: &str, sum: f32| {
+        // Relative, or absolute below 1 like `micro_error`, the sum of the `sin` terms can be close
+        // to 0
+        let error = (sum as f64 - truth) / truth.abs().max(1.0);
+        println!("{name} error:\t{error:+.3e} ({sum})");
+    };
+
+    let cpu_results = [
+        ("f32", "main", compute_shader::compute(options.size)),
+        (
+            "kahan",
+            "main_kahan",
+            compute_shader::compute_kahan(options.size),
+        ),
+    ];
+    for (suffix, _, cpu_result) in cpu_results {
+        print_error(&format!("CPU {suffix}"), cpu_result.sum);
+    }
+    for (suffix, entry_point, cpu_result) in cpu_results {
+        let workload = Workload {
+            entry_point,
+            inputs: &[],
+            output_len: Stats::LEN,
+            settings: [options.size; 4],
+            cpu_result,
+            ..*workload
+        };
+        for (name, output) in bench_variants(options, suffix, rust_gpu, &workload) {
+            print_error(&format!("{name} {suffix}"), output[0]);
+        }
+    }
+}
+
+/// Times each [`micro`] function over `size * size` samples of its domain in every language and
+/// on the CPU, with the error of each against [`micro::reference`].
+fn start_micro(options: &Options, rust_gpu: &ShaderModuleDescriptor<'static>, workload: &Workload) {
+    // The kernels count samples in u32
+    let count = options
+        .size
+        .checked_mul(options.size)
+        .expect("--micro needs --size squared to fit in a u32");
+    let variants = shader_variants(options, rust_gpu, "main_micro");
+    for (kind, name) in MICRO_NAMES.iter().enumerate() {
+        let kind = kind as u32;
+        let inputs: Vec<f32> = (0..count).map(|i| micro::input(kind, i, count)).collect();
+
+        let mut cpu = vec![0.0; count as usize];
+        let start = Instant::now();
+        micro::run(kind, count, &inputs, &mut cpu);
+        let took = start.elapsed();
+        println!(
+            "CPU {name} Took:\t{took:?}\t{}",
+            micro_error(kind, &inputs, &cpu)
+        );
+
+        for (variant, shader_module, entry_point) in &variants {
+            let workload = Workload {
+                entry_point,
+                inputs: &[bytemuck::cast_slice(&inputs)],
+                output_len: count as usize,
+                settings: [count, kind, 0, 0],
+                cpu_result: Stats::new(),
+                ..*workload
+            };
+            let name = format!("{variant} {name}");
+            if let Some((took, gpu)) = catch_failure(&name, || {
+                futures::executor::block_on(start_internal(shader_module.clone(), &workload, false))
+            }) {
+                println!(
+                    "{name} Took:\t{took:?}\t{}",
+                    micro_error(kind, &inputs, &gpu)
+                );
+            }
+        }
+    }
+}
+
+/// Max and mean error of `output`, relative with magnitudes below 1 treated as 1 like the parity
+/// tests.
+fn micro_error(kind: u32, inputs: &[f32], output: &[f32]) -> String {
+    let errors = inputs.iter().zip(output).map(|(x, y)| {
+        let reference = micro::reference(kind, *x);
+        (*y as f64 - reference).abs() / reference.abs().max(1.0)
+    });
+    let (max, sum) = errors.fold((0.0_f64, 0.0), |(max, sum), e| (max.max(e), sum + e));
+    format!(
+        "max error {max:.3e}, mean error {:.3e}",
+        sum / inputs.len() as f64
+    )
+}
+
+/// Runs [`EDGE_CASES`] through `main_intersect` in every shader variant, and their naga
+/// translations with `--naga-roundtrip`, prints each case where a hit isn't bit for bit the CPU's.
+fn start_edge_cases(
+    options: &Options,
+    rust_gpu: &ShaderModuleDescriptor<'static>,
+    workload: &Workload,
+) {
+    let packed: Vec<_> = EDGE_CASES.iter().flat_map(|case| case.pack()).collect();
+    let mut expected = vec![0.0; EDGE_CASES.len() * HIT_LEN];
+    edge_cases::intersect_cases(EDGE_CASES.len() as u32, &packed, &mut expected);
+    let cases: Vec<_> = packed.iter().map(|v| v.to_array()).collect();
+
+    let plain = shader_variants(options, rust_gpu, "main_intersect");
+    let mut variants: Vec<_> = plain
+        .iter()
+        .cloned()
+        .map(|variant| (variant, false))
+        .collect();
+    if options.naga_roundtrip {
+        variants.extend(roundtrip_variants(&plain, workload.adapter_features));
+    }
+
+    for ((name, shader_module, entry_point), passthrough) in variants {
+        let workload = Workload {
+            entry_point,
+            inputs: &[bytemuck::cast_slice(&cases)],
+            output_len: expected.len(),
+            settings: [EDGE_CASES.len() as u32, 0, 0, 0],
+            cpu_result: Stats::new(),
+            ..*workload
+        };
+        let Some((_, output)) = catch_failure(&name, || {
+            futures::executor::block_on(start_internal(shader_module, &workload, passthrough))
+        }) else {
+            continue;
+        };
+
+        let hits = output.chunks(HIT_LEN).zip(expected.chunks(HIT_LEN));
+        let mut mismatches = 0;
+        for (case, (gpu, cpu)) in EDGE_CASES.iter().zip(hits) {
+            if gpu.iter().zip(cpu).any(|(g, c)| g.to_bits() != c.to_bits()) {
+                println!("{name} {}: gpu {gpu:?} != cpu {cpu:?}", case.name);
+                mismatches += 1;
+            }
+        }
+        if mismatches == 0 {
+            println!("{name}: all {} edge cases match the CPU", EDGE_CASES.len());
+        }
+    }
+}
+
+fn start_rng(
+    options: &Options,
+    rust_gpu: &ShaderModuleDescriptor<'static>,
+    workload: &Workload,
+    rng_kind: u32,
+) {
+    let name = RNG_NAMES[rng_kind as usize];
+
+    let start = Instant::now();
+    let cpu_result = compute_shader::compute_rng(options.size, rng_kind, options.sphere_rays);
+    let took = start.elapsed();
+    println!("CPU rng {name} Took:\t{took:?}");
+
+    bench_variants(
+        options,
+        &format!("rng {name}"),
+        rust_gpu,
+        &Workload {
+            entry_point: "main_rng",
+            inputs: &[],
+            output_len: Stats::LEN,
+            settings: [options.size, rng_kind, options.sphere_rays as u32, 0],
+            cpu_result,
+            ..*workload
+        },
+    );
+}
+
+/// Compares the raw bits of every generator in every shader variant, and their naga translations
+/// with `--naga-roundtrip`, against the CPU, exits with an error if any of them differ.
+fn rng_parity(options: &Options, rust_gpu: &ShaderModuleDescriptor<'static>, workload: &Workload) {
+    const COUNT: u32 = 256;
+
+    let mut rng_expected = vec![0; RNG_NAMES.len() * (COUNT * rng::DUMP_DIMENSIONS) as usize];
+    rng::dump(COUNT, &mut rng_expected);
+    let mut hash_expected = vec![0; HASH_NAMES.len() * COUNT as usize];
+    hash_dump(COUNT, &mut hash_expected);
+
+    let rng_exact = bits_match(
+        options,
+        rust_gpu,
+        workload,
+        "main_rng_dump",
+        COUNT,
+        &RNG_NAMES,
+        &rng_expected,
+    );
+    let hash_exact = bits_match(
+        options,
+        rust_gpu,
+        workload,
+        "main_hash_dump",
+        COUNT,
+        &HASH_NAMES,
+        &hash_expected,
+    );
+    if !(rng_exact && hash_exact) {
+        std::process::exit(1);
+    }
+}
+
+/// Runs `entry_point` of every shader with `settings.x = count` and compares its output with
+/// `expected`, one equal sized block per name. Returns false if any bits differ.
+fn bits_match(
+    options: &Options,
+    rust_gpu: &ShaderModuleDescriptor<'static>,
+    workload: &Workload,
+    entry_point: &'static str,
+    count: u32,
+    names: &[&str],
+    expected: &[u32],
+) -> bool {
+    let block = expected.len() / names.len();
+
+    let plain = shader_variants(options, rust_gpu, entry_point);
+    let mut variants: Vec<_> = plain
+        .iter()
+        .cloned()
+        .map(|variant| (variant, false))
+        .collect();
+    if options.naga_roundtrip {
+        variants.extend(roundtrip_variants(&plain, workload.adapter_features));
+    }
+
+    let mut exact = true;
+    for ((name, shader_module, entry_point), passthrough) in variants {
+        let workload = Workload {
+            entry_point,
+            inputs: &[],
+            output_len: expected.len(),
+            settings: [count, 0, 0, 0],
+            cpu_result: Stats::new(),
+            ..*workload
+        };
+        let Some((_, output)) = catch_failure(&name, || {
+            futures::executor::block_on(start_internal(shader_module, &workload, passthrough))
+        }) else {
+            continue;
+        };
+
+        for (kind, block_name) in names.iter().enumerate() {
+            let range = kind * block..(kind + 1) * block;
+            let mismatches = output[range.clone()]
+                .iter()
+                .zip(&expected[range])
+                .filter(|(gpu, cpu)| gpu.to_bits() != **cpu)
+                .count();
+            if mismatches == 0 {
+                println!("{name} {block_name}: bit exact");
+            } else {
+                exact = false;
+                println!("{name} {block_name}: {mismatches} of {block} values differ from the CPU");
+            }
+        }
+    }
+    exact
+}
+
+/// Runs the WGSL, GLSL and Slang `main` kernels in the [`interpreter`] instead of on the GPU and
+/// compares them against [`compute_shader::compute`], exits with an error if any of them differ.
+pub fn interpret(options: &Options) {
+    let start = Instant::now();
+    let cpu_result = compute_shader::compute(options.size);
+    println!("CPU Took:\t{:?}", start.elapsed());
+
+    let glsl = codegen::glsl(WGSL, "main").unwrap_or_else(|err| panic!("{err}"));
+    let mut modules = vec![
+        (
+            "wgsl",
+            naga::front::wgsl::parse_str(WGSL).expect("invalid wgsl"),
+        ),
+        (
+            "glsl",
+            naga::front::glsl::Frontend::default()
+                .parse(&naga::ShaderStage::Compute.into(), &glsl)
+                .unwrap_or_else(|errors| panic!("invalid glsl: {errors:?}")),
+        ),
+    ];
+    prepare_slang(options);
+    let slang_path = slang_module_path(options, "main");
+    if slang_path.exists() {
+        let spv = load_shader_module(&slang_path);
+        let module =
+            naga::front::spv::parse_u8_slice(&spv, &Default::default()).expect("invalid slang spv");
+        modules.push(("slang", module));
+    } else {
+        println!("slang skipped, {SLANG_MISSING}");
+    }
+
+    let mut failed = false;
+    for (name, module) in modules {
+        let settings = [options.size; 4];
+        let mut buffers = vec![
+            vec![0; Stats::LEN * 4],
+            bytemuck::cast_slice(&settings).to_vec(),
+        ];
+        let start = Instant::now();
+        interpreter::run(&module, "main", &mut buffers);
+        println!("{name} interpreted Took:\t{:?}", start.elapsed());
+
+        let result = Stats::read(bytemuck::cast_slice(&buffers[0]), 0);
+        if result == cpu_result {
+            println!("{name}: matches the CPU");
+        } else {
+            failed = true;
+            println!("{name}: differs from the CPU\ncpu:  {cpu_result:?}\n{name}: {result:?}");
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// Returns the whole output buffer.
+fn bench(name: &str, shader_module: ShaderModuleDescriptor<'_>, workload: &Workload) -> Vec<f32> {
+    dump(name, &shader_module.source, workload);
+    let passthrough = workload.spirv_passthrough
+        && matches!(shader_module.source, ShaderSource::SpirV(_))
+        && workload
+            .adapter_features
+            .contains(Features::SPIRV_SHADER_PASSTHROUGH);
+    let gpu_result = run(name, shader_module.clone(), workload, false);
+    if passthrough {
+        run(
+            &format!("{name} passthrough"),
+            shader_module,
+            workload,
+            true,
+        );
+    }
+    gpu_result
+}
+
+fn dump(name: &str, source: &ShaderSource, workload: &Workload) {
+    if let Some(dir) = workload.dump_dir {
+        let stats = spirv_dump::dump(dir, name, source);
+        println!("{name} SPIR-V:\t{}", stats.summary());
+    }
+}
+
+/// Times one run, with `passthrough` like [`start_internal`], returns the whole output buffer.
+fn run(
+    name: &str,
     shader_module: ShaderModuleDescriptor<'_>,
-) -> (Duration, f32) {
+    workload: &Workload,
+    passthrough: bool,
+) -> Vec<f32> {
+    let (gpu_duration, gpu_result) =
+        futures::executor::block_on(start_internal(shader_module, workload, passthrough));
+    println!("{name} Took:\t{:?}", gpu_duration);
+    print_if_not_eq(workload.cpu_result, Stats::read(&gpu_result, 0));
+    gpu_result
+}
+
+async fn request_adapter() -> (Instance, Adapter) {
     let backends = backend_bits_from_env().unwrap_or(Backends::PRIMARY);
     let instance = Instance::new(InstanceDescriptor {
         backends,
@@ -116,15 +1087,63 @@ async fn start_internal(
     let adapter = initialize_adapter_from_env_or_default(&instance, backends, None)
         .await
         .expect("Failed to find an appropriate adapter");
+    (instance, adapter)
+}
+
+/// With `passthrough`, SPIR-V modules are handed to the driver as is instead of going through
+/// naga, which needs [`Features::SPIRV_SHADER_PASSTHROUGH`].
+async fn start_internal(
+    shader_module: ShaderModuleDescriptor<'_>,
+    workload: &Workload<'_>,
+    passthrough: bool,
+) -> (Duration, Vec<f32>) {
+    // Checked before the module goes to wgpu, which would only report a validation error
+    let mut provides = BTreeMap::from([
+        (0, BufferBindingType::Storage { read_only: false }),
+        (1, BufferBindingType::Uniform),
+    ]);
+    for i in 0..workload.inputs.len() {
+        provides.insert(2 + i as u32, BufferBindingType::Storage { read_only: true });
+    }
+    let bindings = reflect::bindings(&shader_module.source, workload.entry_point)
+        .and_then(|bindings| reflect::check(&bindings, &provides).map(|_| bindings))
+        .unwrap_or_else(|err| {
+            panic!(
+                "`{}` doesn't match the workload: {err}",
+                workload.entry_point
+            )
+        });
+    // `settings` as push constants instead of the uniform, see [`Settings::PushConstant`]
+    let push_constant_size =
+        reflect::push_constant_size(&shader_module.source, workload.entry_point)
+            .unwrap_or_else(|err| panic!("{err}"));
+    let settings_bytes = bytemuck::bytes_of(&workload.settings);
+    assert!(
+        push_constant_size as usize <= settings_bytes.len(),
+        "`{}` has {push_constant_size} bytes of push constants, the settings are only {}",
+        workload.entry_point,
+        settings_bytes.len()
+    );
+
+    let (instance, adapter) = request_adapter().await;
 
-    let features = Features::TIMESTAMP_QUERY | Features::TIMESTAMP_QUERY_INSIDE_PASSES;
+    let mut features = Features::TIMESTAMP_QUERY | Features::TIMESTAMP_QUERY_INSIDE_PASSES;
+    if passthrough {
+        features |= Features::SPIRV_SHADER_PASSTHROUGH;
+    }
+    if push_constant_size > 0 {
+        features |= Features::PUSH_CONSTANTS;
+    }
 
     let (device, queue) = adapter
         .request_device(
             &DeviceDescriptor {
                 label: None,
                 features,
-                limits: Limits::default(),
+                limits: Limits {
+                    max_push_constant_size: push_constant_size,
+                    ..Limits::default()
+                },
             },
             None,
         )
@@ -133,48 +1152,49 @@ async fn start_internal(
     drop(instance);
     drop(adapter);
 
-    let module = device.create_shader_module(shader_module);
+    let module = match &shader_module.source {
+        ShaderSource::SpirV(words) if passthrough => {
+            // SAFETY: the modules come from rust-gpu, slangc or naga, which are trusted to produce
+            // valid SPIR-V, and the same modules pass naga's validation in the default path.
+            unsafe {
+                device.create_shader_module_spirv(&ShaderModuleDescriptorSpirV {
+                    label: shader_module.label,
+                    source: words.clone(),
+                })
+            }
+        }
+        _ => device.create_shader_module(shader_module),
+    };
+
+    let layout_entries = reflect::layout_entries(&bindings);
 
     let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
         label: None,
-        entries: &[
-            BindGroupLayoutEntry {
-                binding: 0,
-                count: None,
-                visibility: ShaderStages::COMPUTE,
-                ty: BindingType::Buffer {
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                    ty: BufferBindingType::Storage { read_only: false },
-                },
-            },
-            BindGroupLayoutEntry {
-                binding: 1,
-                count: None,
-                visibility: ShaderStages::COMPUTE,
-                ty: BindingType::Buffer {
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                    ty: BufferBindingType::Uniform,
-                },
-            },
-        ],
+        entries: &layout_entries,
     });
 
+    let mut push_constant_ranges = Vec::new();
+    if push_constant_size > 0 {
+        push_constant_ranges.push(PushConstantRange {
+            stages: ShaderStages::COMPUTE,
+            range: 0..push_constant_size,
+        });
+    }
+
     let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
         label: None,
         bind_group_layouts: &[&bind_group_layout],
-        push_constant_ranges: &[],
+        push_constant_ranges: &push_constant_ranges,
     });
 
     let compute_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
         label: None,
         layout: Some(&pipeline_layout),
         module: &module,
-        entry_point: "main",
+        entry_point: workload.entry_point,
     });
 
-    let storage_buffer_size = 4;
+    let storage_buffer_size = (workload.output_len * 4) as u64;
 
     let readback_buffer = device.create_buffer(&BufferDescriptor {
         label: None,
@@ -186,31 +1206,46 @@ async fn start_internal(
 
     let storage_buffer = device.create_buffer_init(&util::BufferInitDescriptor {
         label: Some("Bench Input"),
-        contents: &[0_u8; 4],
+        contents: &vec![0_u8; workload.output_len * 4],
         usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
     });
 
     let uniform_buffer = device.create_buffer_init(&util::BufferInitDescriptor {
         label: Some("Bench Uniform"),
-        contents: &bytemuck::bytes_of(&[options.size; 4]),
+        contents: settings_bytes,
         usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
     });
 
     let timestamp = Timestamp::new(&device, &queue);
 
+    let input_buffers = workload
+        .inputs
+        .iter()
+        .map(|contents| {
+            device.create_buffer_init(&util::BufferInitDescriptor {
+                label: Some("Bench Scene"),
+                contents,
+                usage: BufferUsages::STORAGE,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let entries: Vec<_> = bindings
+        .keys()
+        .map(|&binding| BindGroupEntry {
+            binding,
+            resource: match binding {
+                0 => storage_buffer.as_entire_binding(),
+                1 => uniform_buffer.as_entire_binding(),
+                _ => input_buffers[binding as usize - 2].as_entire_binding(),
+            },
+        })
+        .collect();
+
     let bind_group = device.create_bind_group(&BindGroupDescriptor {
         label: None,
         layout: &bind_group_layout,
-        entries: &[
-            BindGroupEntry {
-                binding: 0,
-                resource: storage_buffer.as_entire_binding(),
-            },
-            BindGroupEntry {
-                binding: 1,
-                resource: uniform_buffer.as_entire_binding(),
-            },
-        ],
+        entries: &entries,
     });
 
     let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor { label: None });
@@ -219,6 +1254,9 @@ async fn start_internal(
         let mut cpass = encoder.begin_compute_pass(&ComputePassDescriptor { label: None });
         cpass.set_bind_group(0, &bind_group, &[]);
         cpass.set_pipeline(&compute_pipeline);
+        if push_constant_size > 0 {
+            cpass.set_push_constants(0, &settings_bytes[..push_constant_size as usize]);
+        }
         // Warm up
         cpass.dispatch_workgroups(1, 1, 1);
         cpass.dispatch_workgroups(1, 1, 1);
@@ -249,5 +1287,5 @@ async fn start_internal(
     drop(data);
     readback_buffer.unmap();
 
-    (timestamp.unmap(timestamp_slice), *result.first().unwrap())
+    (timestamp.unmap(timestamp_slice), result)
 }
@@ -1,9 +1,36 @@
 @group(0) @binding(0) var<storage, read_write> data: array<f32>;
 @group(0) @binding(1) var<uniform> settings: vec4<u32>;
+// Same buffer as `data`, only used by main_rng_dump and main_hash_dump
+@group(0) @binding(0) var<storage, read_write> data_u32: array<u32>;
+
+// min.w: bits of the left child / first triangle index, max.w: bits of the triangle count (0 for
+// interior nodes)
+struct BvhNode {
+    min: vec4<f32>,
+    max: vec4<f32>,
+}
+
+@group(0) @binding(2) var<storage, read> triangles: array<vec4<f32>>;
+@group(0) @binding(3) var<storage, read> nodes: array<BvhNode>;
+// Same buffer as `triangles`, only used by main_micro
+@group(0) @binding(2) var<storage, read> micro_inputs: array<f32>;
 
 const F32_MAX: f32 = 3.402823466E+38;
+const PI: f32 = 3.14159265358979323846264338327950288;
+const TAU: f32 = 6.28318530717958647692528676655900577;
+// naga wants literals for array sizes and switch cases, so these are repeated there
+const BVH_STACK_SIZE: u32 = 32u;
+const RNG_HASH: u32 = 0u;
+const RNG_PCG32: u32 = 1u;
+const RNG_XORSHIFT: u32 = 2u;
+const RNG_SOBOL: u32 = 3u;
+const RNG_R2: u32 = 4u;
+const RNG_COUNT: u32 = 5u;
+const DUMP_DIMENSIONS: u32 = 16u;
+// The size main_constant runs, written in before the kernel is compiled, see `settings.rs`
+const SIZE_CONSTANT: u32 = 0u;
 
-fn uhash(a: u32, b: u32) -> u32 { 
+fn uhash(a: u32, b: u32) -> u32 {
     var x = ((a * 1597334673u) ^ (b * 3812015801u));
     // from https://nullprogram.com/blog/2018/07/31/
     x = x ^ (x >> 16u);
@@ -14,8 +41,8 @@ fn uhash(a: u32, b: u32) -> u32 {
     return x;
 }
 
-fn unormf(n: u32) -> f32 { 
-    return f32(n) * (1.0 / f32(0xffffffffu)); 
+fn unormf(n: u32) -> f32 {
+    return f32(n) * (1.0 / f32(0xffffffffu));
 }
 
 fn hash_noise(ufrag_coord: vec2<u32>, frame: u32) -> f32 {
@@ -23,65 +50,568 @@ fn hash_noise(ufrag_coord: vec2<u32>, frame: u32) -> f32 {
     return unormf(urnd);
 }
 
-fn intersect(p0: vec3<f32>, p1: vec3<f32>, p2: vec3<f32>, origin: vec3<f32>, direction: vec3<f32>) -> vec3<f32> {
+// Generator picked at runtime by `kind`, see `rng.rs` in the shader crate
+struct Rng {
+    kind: u32,
+    index: u32,
+    state: u32,
+    dimension: u32,
+}
+
+fn sample_index(coord: vec2<u32>) -> u32 {
+    return (coord.x << 16u) ^ coord.y;
+}
+
+fn pcg32_output(s: u32) -> u32 {
+    let word = ((s >> ((s >> 28u) + 4u)) ^ s) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn xorshift32(state: u32) -> u32 {
+    var x = state;
+    x ^= x << 13u;
+    x ^= x >> 17u;
+    x ^= x << 5u;
+    return x;
+}
+
+fn sobol_dim1(index: u32) -> u32 {
+    var result = 0u;
+    var v = 1u << 31u;
+    var i = index;
+    while (i != 0u) {
+        if ((i & 1u) != 0u) {
+            result ^= v;
+        }
+        i >>= 1u;
+        v ^= v >> 1u;
+    }
+    return result;
+}
+
+fn laine_karras_permutation(x_in: u32, seed: u32) -> u32 {
+    var x = x_in + seed;
+    x ^= x * 0x6c50b47cu;
+    x ^= x * 0xb82f1e52u;
+    x ^= x * 0xc7afe638u;
+    x ^= x * 0x8d22f6e6u;
+    return x;
+}
+
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    return reverseBits(laine_karras_permutation(reverseBits(x), seed));
+}
+
+fn sobol(index: u32, dimension: u32, seed: u32) -> u32 {
+    let pair_seed = uhash(dimension >> 1u, seed);
+    let shuffled = nested_uniform_scramble(index, pair_seed);
+    var x = 0u;
+    if ((dimension & 1u) == 0u) {
+        x = reverseBits(shuffled);
+    } else {
+        x = sobol_dim1(shuffled);
+    }
+    return nested_uniform_scramble(x, uhash(pair_seed, dimension));
+}
+
+fn r2(index: u32, dimension: u32, seed: u32) -> u32 {
+    // 2^32 / g and 2^32 / g^2, with g the plastic number
+    var alpha = 0x91e10da6u;
+    if ((dimension & 1u) == 0u) {
+        alpha = 0xc13fa9a9u;
+    }
+    return uhash(dimension >> 1u, seed) + index * alpha;
+}
+
+fn new_rng(kind: u32, coord: vec2<u32>, seed: u32) -> Rng {
+    let hash_state = (coord.y << 11u) + seed;
+    var rng = Rng(kind, coord.x, hash_state, 0u);
+    switch (kind) {
+        case 1u: {
+            rng.index = 0u;
+            rng.state = uhash(coord.x, hash_state);
+        }
+        case 2u: {
+            rng.index = 0u;
+            // The all zero state never leaves zero
+            rng.state = max(uhash(coord.x, hash_state), 1u);
+        }
+        case 3u, 4u: {
+            rng.index = sample_index(coord);
+            rng.state = seed;
+        }
+        default: {
+        }
+    }
+    return rng;
+}
+
+fn rng_next(rng: ptr<function, Rng>) -> u32 {
+    let r = *rng;
+    (*rng).dimension = r.dimension + 1u;
+    var x = 0u;
+    switch (r.kind) {
+        case 1u: {
+            (*rng).state = r.state * 747796405u + 2891336453u;
+            x = pcg32_output(r.state);
+        }
+        case 2u: {
+            x = xorshift32(r.state);
+            (*rng).state = x;
+        }
+        case 3u: {
+            x = sobol(r.index, r.dimension, r.state);
+        }
+        case 4u: {
+            x = r2(r.index, r.dimension, r.state);
+        }
+        default: {
+            x = uhash(r.index, r.state + r.dimension);
+        }
+    }
+    return x;
+}
+
+fn rng_vec3(rng: ptr<function, Rng>) -> vec3<f32> {
+    let x = rng_next(rng);
+    let y = rng_next(rng);
+    let z = rng_next(rng);
+    return vec3<f32>(unormf(x), unormf(y), unormf(z));
+}
+
+fn uniform_sphere(u: vec2<f32>) -> vec3<f32> {
+    let z = 1.0 - 2.0 * u.x;
+    let r = sqrt(max(1.0 - z * z, 0.0));
+    let phi = TAU * u.y;
+    return vec3<f32>(r * cos(phi), r * sin(phi), z);
+}
+
+struct Ray {
+    origin: vec3<f32>,
+    direction: vec3<f32>,
+    // Hits are only reported for t_min < t < t_max
+    t_min: f32,
+    t_max: f32,
+}
+
+struct Hit {
+    t: f32,
+    u: f32,
+    v: f32,
+    front_face: bool,
+}
+
+// Written to the start of `data` in field order
+struct Stats {
+    sum: f32,
+    nearest_t: f32,
+    hit_count: f32,
+}
+
+fn new_ray(origin: vec3<f32>, direction: vec3<f32>) -> Ray {
+    return Ray(origin, direction, 0.0, F32_MAX);
+}
+
+fn miss() -> Hit {
+    return Hit(F32_MAX, 0.0, 0.0, false);
+}
+
+fn is_hit(hit: Hit) -> bool {
+    return hit.t < F32_MAX;
+}
+
+fn new_stats() -> Stats {
+    return Stats(0.0, F32_MAX, 0.0);
+}
+
+fn add_hit(stats: Stats, hit: Hit) -> Stats {
+    var result = stats;
+    result.sum += sin(min(hit.t, 100.0));
+    if (is_hit(hit)) {
+        result.nearest_t = min(result.nearest_t, hit.t);
+        result.hit_count += 1.0;
+    }
+    return result;
+}
+
+fn write_stats(index: u32, stats: Stats) {
+    data[index] = stats.sum;
+    data[index + 1u] = stats.nearest_t;
+    data[index + 2u] = stats.hit_count;
+}
+
+// Kahan summation, `compensation` carries the low bits lost from `sum` to the next add
+struct KahanSum {
+    sum: f32,
+    compensation: f32,
+}
+
+fn kahan_add(kahan: KahanSum, value: f32) -> KahanSum {
+    let term = value - kahan.compensation;
+    let sum = kahan.sum + term;
+    return KahanSum(sum, (sum - kahan.sum) - term);
+}
+
+fn intersect(p0: vec3<f32>, p1: vec3<f32>, p2: vec3<f32>, ray: Ray) -> Hit {
     let e1 = p0 - p1;
     let e2 = p2 - p0;
     let n = cross(e1, e2);
-    
-    let c = p0 - origin;
-    let r = cross(direction, c);
-    let inv_det = 1.0 / dot(n, direction);
 
-    var uvt = vec3(
-        dot(r, e2), 
+    let c = p0 - ray.origin;
+    let r = cross(ray.direction, c);
+    let inv_det = 1.0 / dot(n, ray.direction);
+
+    var uvt = vec3<f32>(
+        dot(r, e2),
         dot(r, e1),
         dot(n, c)
     ) * inv_det;
 
-    //if all(uvt > vec3(0.0)) && uvt.x + uvt.y < 1.0 {
-    if uvt.x > 0.0 && uvt.y > 0.0 && uvt.z > 0.0 && uvt.x + uvt.y < 1.0 {
-        return uvt;
+    if (uvt.x > 0.0 && uvt.y > 0.0 && uvt.x + uvt.y < 1.0 && uvt.z > ray.t_min && uvt.z < ray.t_max) {
+        // n points away from the side the (p0, p1, p2) winding faces
+        return Hit(uvt.z, uvt.x, uvt.y, inv_det > 0.0);
     }
 
-    return vec3(F32_MAX);
+    return miss();
+}
+
+fn random_ray(coord: vec2<u32>) -> Ray {
+    let origin = vec3<f32>(
+        hash_noise(coord, 9u),
+        hash_noise(coord, 10u),
+        hash_noise(coord, 11u)
+    ) * 2.0 - 1.0;
+    let direction = vec3<f32>(
+        hash_noise(coord, 12u),
+        hash_noise(coord, 13u),
+        hash_noise(coord, 14u)
+    ) * 2.0 - 1.0;
+    return new_ray(origin, normalize(direction));
+}
+
+// Pinhole camera at (0, 0, -3) looking down +z, the 1x1 image plane at a distance of 1 makes the
+// front face of the -1..1 cube exactly fill the image
+fn camera_ray(coord: vec2<u32>, size: u32) -> Ray {
+    let uv = (vec2<f32>(coord) + 0.5) / f32(size);
+    let direction = vec3<f32>(uv.x - 0.5, 0.5 - uv.y, 1.0);
+    return new_ray(vec3<f32>(0.0, 0.0, -3.0), normalize(direction));
+}
+
+fn node_hit(node: BvhNode, ray: Ray, inv_dir: vec3<f32>) -> bool {
+    let t0 = (node.min.xyz - ray.origin) * inv_dir;
+    let t1 = (node.max.xyz - ray.origin) * inv_dir;
+    let lo = min(t0, t1);
+    let hi = max(t0, t1);
+    let t_near = max(max(lo.x, max(lo.y, lo.z)), ray.t_min);
+    let t_far = min(min(hi.x, min(hi.y, hi.z)), ray.t_max);
+    return t_near <= t_far;
+}
+
+// ray.t_max is pulled in to each hit found on the way, so only closer nodes and triangles are
+// tested after it
+fn closest_hit(ray_in: Ray) -> Hit {
+    var ray = ray_in;
+    let inv_dir = 1.0 / ray.direction;
+    var closest = miss();
+
+    var stack: array<u32, 32>;
+    stack[0] = 0u;
+    var stack_len = 1u;
+
+    while (stack_len > 0u) {
+        stack_len -= 1u;
+        let node = nodes[stack[stack_len]];
+        if (node_hit(node, ray, inv_dir)) {
+            let left_first = bitcast<u32>(node.min.w);
+            let count = bitcast<u32>(node.max.w);
+            if (count > 0u) {
+                for (var i = 0u; i < count; i += 1u) {
+                    let tri = (left_first + i) * 3u;
+                    let hit = intersect(
+                        triangles[tri].xyz,
+                        triangles[tri + 1u].xyz,
+                        triangles[tri + 2u].xyz,
+                        ray
+                    );
+                    if (is_hit(hit)) {
+                        closest = hit;
+                        ray.t_max = hit.t;
+                    }
+                }
+            } else {
+                stack[stack_len] = left_first + 1u;
+                stack[stack_len + 1u] = left_first;
+                stack_len += 2u;
+            }
+        }
+    }
+
+    return closest;
+}
+
+@compute @workgroup_size(1, 1, 1)
+fn main_bvh(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    var stats = new_stats();
+    for (var x = 0u; x < settings.x; x += 1u) {
+        for (var y = 0u; y < settings.x; y += 1u) {
+            let coord = vec2<u32>(x, y);
+            stats = add_hit(stats, closest_hit(random_ray(coord)));
+        }
+    }
+
+    let index = invocation_id.x;
+    write_stats(index, stats);
+}
+
+@compute @workgroup_size(1, 1, 1)
+fn main_camera(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let index = invocation_id.x;
+
+    var stats = new_stats();
+    for (var x = 0u; x < settings.x; x += 1u) {
+        for (var y = 0u; y < settings.x; y += 1u) {
+            let coord = vec2<u32>(x, y);
+            let hit = closest_hit(camera_ray(coord, settings.x));
+            data[index + 3u + y * settings.x + x] = hit.t;
+            stats = add_hit(stats, hit);
+        }
+    }
+
+    write_stats(index, stats);
+}
+
+@compute @workgroup_size(1, 1, 1)
+fn main_rng(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    var stats = new_stats();
+    for (var x = 0u; x < settings.x; x += 1u) {
+        for (var y = 0u; y < settings.x; y += 1u) {
+            var rng = new_rng(settings.y, vec2<u32>(x, y), 0u);
+            let a = rng_vec3(&rng) * 2.0 - 1.0;
+            let b = rng_vec3(&rng) * 2.0 - 1.0;
+            let c = rng_vec3(&rng) * 2.0 - 1.0;
+            let origin = rng_vec3(&rng) * 2.0 - 1.0;
+            var direction: vec3<f32>;
+            if (settings.z != 0u) {
+                let u = rng_next(&rng);
+                let v = rng_next(&rng);
+                direction = uniform_sphere(vec2<f32>(unormf(u), unormf(v)));
+            } else {
+                let cube = rng_vec3(&rng) * 2.0 - 1.0;
+                direction = normalize(cube);
+            }
+            stats = add_hit(stats, intersect(a, b, c, new_ray(origin, direction)));
+        }
+    }
+
+    let index = invocation_id.x;
+    write_stats(index, stats);
+}
+
+@compute @workgroup_size(1, 1, 1)
+fn main_rng_dump() {
+    let count = settings.x;
+    for (var kind = 0u; kind < RNG_COUNT; kind += 1u) {
+        for (var i = 0u; i < count; i += 1u) {
+            var rng = new_rng(kind, vec2<u32>(i >> 4u, i & 15u), 0u);
+            for (var dimension = 0u; dimension < DUMP_DIMENSIONS; dimension += 1u) {
+                data_u32[(kind * count + i) * DUMP_DIMENSIONS + dimension] = rng_next(&rng);
+            }
+        }
+    }
+}
+
+// See `hash_dump` in `util.rs` of the shader crate
+@compute @workgroup_size(1, 1, 1)
+fn main_hash_dump() {
+    let count = settings.x;
+    for (var i = 0u; i < count; i += 1u) {
+        let a = i * 0x9e3779b9u;
+        let b = ~i;
+        data_u32[i] = uhash(a, b);
+        data_u32[count + i] = bitcast<u32>(hash_noise(vec2<u32>(a, i >> 4u), i & 15u));
+        data_u32[2u * count + i] = bitcast<u32>(unormf(a));
+    }
+}
+
+fn compute(size: u32) -> Stats {
+    var stats = new_stats();
+    for (var x = 0u; x < size; x += 1u) {
+        for (var y = 0u; y < size; y += 1u) {
+            let coord = vec2<u32>(x, y);
+            let a = vec3<f32>(
+                hash_noise(coord, 0u),
+                hash_noise(coord, 1u),
+                hash_noise(coord, 2u)
+            ) * 2.0 - 1.0;
+            let b = vec3<f32>(
+                hash_noise(coord, 3u),
+                hash_noise(coord, 4u),
+                hash_noise(coord, 5u)
+            ) * 2.0 - 1.0;
+            let c = vec3<f32>(
+                hash_noise(coord, 6u),
+                hash_noise(coord, 7u),
+                hash_noise(coord, 8u)
+            ) * 2.0 - 1.0;
+            stats = add_hit(stats, intersect(a, b, c, random_ray(coord)));
+        }
+    }
+
+    return stats;
 }
 
 @compute @workgroup_size(1, 1, 1)
 fn main(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
-    
-    var sum = 0.0;
+    let stats = compute(settings.x);
+    let index = invocation_id.x;
+    write_stats(index, stats);
+}
+
+// `main` with the size known when it's compiled, for `--settings-variants`
+@compute @workgroup_size(1, 1, 1)
+fn main_constant(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let stats = compute(SIZE_CONSTANT);
+    let index = invocation_id.x;
+    write_stats(index, stats);
+}
+
+// `main` with `while` loops, for `--loop-forms`
+@compute @workgroup_size(1, 1, 1)
+fn main_while(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    var stats = new_stats();
+    var x = 0u;
+    while (x < settings.x) {
+        var y = 0u;
+        while (y < settings.x) {
+            let coord = vec2<u32>(x, y);
+            let a = vec3<f32>(
+                hash_noise(coord, 0u),
+                hash_noise(coord, 1u),
+                hash_noise(coord, 2u)
+            ) * 2.0 - 1.0;
+            let b = vec3<f32>(
+                hash_noise(coord, 3u),
+                hash_noise(coord, 4u),
+                hash_noise(coord, 5u)
+            ) * 2.0 - 1.0;
+            let c = vec3<f32>(
+                hash_noise(coord, 6u),
+                hash_noise(coord, 7u),
+                hash_noise(coord, 8u)
+            ) * 2.0 - 1.0;
+            stats = add_hit(stats, intersect(a, b, c, random_ray(coord)));
+            y += 1u;
+        }
+        x += 1u;
+    }
+
+    let index = invocation_id.x;
+    write_stats(index, stats);
+}
+
+// `main` with the sum accumulated with Kahan summation
+@compute @workgroup_size(1, 1, 1)
+fn main_kahan(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    var stats = new_stats();
+    var kahan = KahanSum(0.0, 0.0);
     for (var x = 0u; x < settings.x; x += 1u) {
         for (var y = 0u; y < settings.x; y += 1u) {
-                let coord = vec2(x, y);
-                let a = vec3(
-                    hash_noise(coord, 0u),
-                    hash_noise(coord, 1u),
-                    hash_noise(coord, 2u),
-                ) * 2.0 - 1.0;
-                let b = vec3(
-                    hash_noise(coord, 3u),
-                    hash_noise(coord, 4u),
-                    hash_noise(coord, 5u),
-                ) * 2.0 - 1.0;
-                let c = vec3(
-                    hash_noise(coord, 6u),
-                    hash_noise(coord, 7u),
-                    hash_noise(coord, 8u),
-                ) * 2.0 - 1.0;
-                let origin = vec3(
-                    hash_noise(coord, 9u),
-                    hash_noise(coord, 10u),
-                    hash_noise(coord, 11u),
-                ) * 2.0 - 1.0;
-                let direction = vec3(
-                    hash_noise(coord, 12u),
-                    hash_noise(coord, 13u),
-                    hash_noise(coord, 14u),
-                ) * 2.0 - 1.0;
-                sum += sin(min(intersect(a, b, c, origin, normalize(direction)).y, 100.0));
+            let coord = vec2<u32>(x, y);
+            let a = vec3<f32>(
+                hash_noise(coord, 0u),
+                hash_noise(coord, 1u),
+                hash_noise(coord, 2u)
+            ) * 2.0 - 1.0;
+            let b = vec3<f32>(
+                hash_noise(coord, 3u),
+                hash_noise(coord, 4u),
+                hash_noise(coord, 5u)
+            ) * 2.0 - 1.0;
+            let c = vec3<f32>(
+                hash_noise(coord, 6u),
+                hash_noise(coord, 7u),
+                hash_noise(coord, 8u)
+            ) * 2.0 - 1.0;
+            let hit = intersect(a, b, c, random_ray(coord));
+            kahan = kahan_add(kahan, sin(min(hit.t, 100.0)));
+            stats = add_hit(stats, hit);
         }
     }
+    stats.sum = kahan.sum;
 
     let index = invocation_id.x;
-    data[index] = sum;
-}
\ No newline at end of file
+    write_stats(index, stats);
+}
+
+// The cases of `compute_shader::edge_cases` packed in `triangles`, 5 vec4s each: a, b, c,
+// (origin, t_min) and (direction, t_max). Writes t, u, v and front_face of each hit to `data`.
+@compute @workgroup_size(1, 1, 1)
+fn main_intersect() {
+    for (var i = 0u; i < settings.x; i += 1u) {
+        let base = i * 5u;
+        let origin = triangles[base + 3u];
+        let direction = triangles[base + 4u];
+        let ray = Ray(origin.xyz, direction.xyz, origin.w, direction.w);
+        let p0 = triangles[base].xyz;
+        let p1 = triangles[base + 1u].xyz;
+        let p2 = triangles[base + 2u].xyz;
+        let hit = intersect(p0, p1, p2, ray);
+        data[i * 4u] = hit.t;
+        data[i * 4u + 1u] = hit.u;
+        data[i * 4u + 2u] = hit.v;
+        data[i * 4u + 3u] = f32(hit.front_face);
+    }
+}
+
+// See `acos_approx` in `util.rs` of the shader crate
+fn acos_approx(v: f32) -> f32 {
+    let x = abs(v);
+    var res = -0.155972 * x + 1.56467;
+    res = res * sqrt(1.0 - x);
+    if (v >= 0.0) {
+        return res;
+    }
+    return PI - res;
+}
+
+// See `eval` in `micro.rs` of the shader crate, the cases are its `MICRO_*` kinds
+fn micro_eval(kind: u32, x: f32) -> f32 {
+    var y = x;
+    switch (kind) {
+        case 1u: {
+            y = sin(x);
+        }
+        case 2u: {
+            y = 1.0 / x;
+        }
+        case 3u: {
+            y = normalize(vec3<f32>(x, 0.5, -0.25)).x;
+        }
+        case 4u: {
+            y = acos_approx(x);
+        }
+        case 5u: {
+            y = pow(x, 2.2);
+        }
+        case 6u: {
+            y = exp(x);
+        }
+        case 7u: {
+            y = smoothstep(0.0, 1.0, x);
+        }
+        case 8u: {
+            y = clamp(x, 0.0, 1.0);
+        }
+        default: {
+        }
+    }
+    return y;
+}
+
+// Evaluates function `settings.y` of `compute_shader::micro` at the first `settings.x` inputs
+@compute @workgroup_size(1, 1, 1)
+fn main_micro() {
+    for (var i = 0u; i < settings.x; i += 1u) {
+        data[i] = micro_eval(settings.y, micro_inputs[i]);
+    }
+}
@@ -0,0 +1,11 @@
+// `main` with `settings` from push constants, for `--settings-variants`. Only appended to
+// compute_shader.wgsl for it, naga rejects a module declaring push constants on adapters without
+// them, whichever entry point runs.
+var<push_constant> push_settings: vec4<u32>;
+
+@compute @workgroup_size(1, 1, 1)
+fn main_push_constant(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let stats = compute(push_settings.x);
+    let index = invocation_id.x;
+    write_stats(index, stats);
+}
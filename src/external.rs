@@ -0,0 +1,61 @@
+//! Kernels loaded at runtime with `--shader`, benchmarked next to the built-in ones. They get the
+//! same bindings as the built-in `main` kernel: the output buffer at binding 0 and the settings
+//! uniform at binding 1. A kernel declaring anything else is reported by [`crate::reflect`].
+
+use std::{borrow::Cow, path::Path};
+use wgpu::{ShaderModuleDescriptor, ShaderSource};
+
+/// Loads the `.wgsl`, `.spv` or `.comp` (GLSL) kernel at `path`, returns the module and the entry
+/// point to run. GLSL entry points are always `main`, `entry_point` is passed upper cased as a
+/// define so one file can hold several of them.
+pub fn load(
+    path: &Path,
+    entry_point: &str,
+) -> Result<(ShaderModuleDescriptor<'static>, String), String> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let read = || std::fs::read(path).map_err(|err| format!("unable to read {path:?}: {err}"));
+    let text = || String::from_utf8(read()?).map_err(|_| format!("{path:?} isn't valid utf-8"));
+    let (source, entry_point) = match extension {
+        "wgsl" => (ShaderSource::Wgsl(Cow::Owned(text()?)), entry_point),
+        "spv" => (
+            ShaderSource::SpirV(Cow::Owned(spirv_words(path, &read()?)?)),
+            entry_point,
+        ),
+        "comp" => (
+            ShaderSource::Glsl {
+                shader: Cow::Owned(text()?),
+                stage: naga::ShaderStage::Compute,
+                defines: [(entry_point.to_uppercase(), "1".to_string())]
+                    .into_iter()
+                    .collect(),
+            },
+            "main",
+        ),
+        _ => {
+            return Err(format!(
+                "{path:?} isn't a .wgsl, .spv or .comp (GLSL compute) shader"
+            ))
+        }
+    };
+    let module = ShaderModuleDescriptor {
+        label: None,
+        source,
+    };
+    Ok((module, entry_point.to_string()))
+}
+
+fn spirv_words(path: &Path, bytes: &[u8]) -> Result<Vec<u32>, String> {
+    let chunks = bytes.chunks_exact(4);
+    if !chunks.remainder().is_empty() {
+        return Err(format!("{path:?} isn't a whole number of SPIR-V words"));
+    }
+    let words: Vec<_> = chunks
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        .collect();
+    if words.first() != Some(&0x07230203) {
+        return Err(format!(
+            "{path:?} doesn't start with the SPIR-V magic number"
+        ));
+    }
+    Ok(words)
+}
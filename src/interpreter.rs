@@ -0,0 +1,886 @@
+//! A small interpreter for naga IR, so the WGSL and Slang kernels can be checked on machines
+//! without a GPU. It only covers what the kernels in this repo use: scalars, vectors, arrays and
+//! structs of 32 bit values, the usual arithmetic and math functions, loops, switches, function
+//! calls, storage/uniform buffers and push constants. Matrices, textures and atomics are not
+//! supported.
+
+use naga::{
+    BinaryOperator, Block, ConstantInner, Expression, Function, Handle, MathFunction, Module,
+    RelationalFunction, ScalarKind, ScalarValue, Statement, StorageAccess, SwitchValue, Type,
+    TypeInner, UnaryOperator,
+};
+
+/// Runs `entry_point` of `module` as a single invocation with a `global_invocation_id` of zero.
+/// `buffers[i]` holds the contents of `@group(0) @binding(i)`, writable storage buffers are
+/// updated with what the shader stored to them.
+/// Bindings past the end of `buffers` start out zeroed, with runtime sized arrays empty.
+pub fn run(module: &Module, entry_point: &str, buffers: &mut [Vec<u8>]) {
+    run_with_push_constants(module, entry_point, buffers, &[]);
+}
+
+/// [`run`] with `push_constants` holding the contents of the push constant globals, which are
+/// zeroed when it's empty.
+pub fn run_with_push_constants(
+    module: &Module,
+    entry_point: &str,
+    buffers: &mut [Vec<u8>],
+    push_constants: &[u8],
+) {
+    let entry_point = module
+        .entry_points
+        .iter()
+        .find(|e| e.name == entry_point)
+        .unwrap_or_else(|| panic!("entry point {entry_point:?} not found"));
+
+    let globals: Vec<_> = module
+        .global_variables
+        .iter()
+        .map(|(_, global)| {
+            let bytes = match global.space {
+                naga::AddressSpace::PushConstant => Some(push_constants).filter(|b| !b.is_empty()),
+                _ => global
+                    .binding
+                    .as_ref()
+                    .and_then(|binding| buffers.get(binding.binding as usize))
+                    .map(Vec::as_slice),
+            };
+            match (bytes, global.init) {
+                (Some(bytes), _) => read(module, global.ty, bytes),
+                (None, Some(init)) => constant(module, init),
+                // Including bindings the entry point doesn't use.
+                (None, None) => zero(module, global.ty),
+            }
+        })
+        .collect();
+
+    let mut interpreter = Interpreter {
+        module,
+        stored: vec![false; globals.len()],
+        globals,
+        stack: Vec::new(),
+    };
+    // The only builtin the kernels read is `global_invocation_id`.
+    let args = entry_point
+        .function
+        .arguments
+        .iter()
+        .map(|arg| zero(module, arg.ty))
+        .collect();
+    interpreter.call(&entry_point.function, args);
+
+    let globals = interpreter.globals.iter().zip(&interpreter.stored);
+    for ((_, global), (value, stored)) in module.global_variables.iter().zip(globals) {
+        // Several globals can alias one binding, only write back the ones that changed.
+        if !stored {
+            continue;
+        }
+        if let (Some(binding), naga::AddressSpace::Storage { access }) =
+            (&global.binding, global.space)
+        {
+            let bytes = buffers.get_mut(binding.binding as usize);
+            if let (true, Some(bytes)) = (access.contains(StorageAccess::STORE), bytes) {
+                write(module, global.ty, value, bytes);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Bool(bool),
+    I32(i32),
+    U32(u32),
+    F32(f32),
+    /// Vectors, arrays and structs.
+    Composite(Vec<Value>),
+    Pointer(Pointer),
+}
+
+impl Value {
+    fn as_bool(&self) -> bool {
+        match self {
+            Value::Bool(b) => *b,
+            _ => panic!("expected a bool, got {self:?}"),
+        }
+    }
+
+    fn as_index(&self) -> usize {
+        match self {
+            Value::I32(i) => *i as usize,
+            Value::U32(u) => *u as usize,
+            _ => panic!("expected an index, got {self:?}"),
+        }
+    }
+
+    fn as_pointer(&self) -> &Pointer {
+        match self {
+            Value::Pointer(p) => p,
+            _ => panic!("expected a pointer, got {self:?}"),
+        }
+    }
+
+    fn components(&self) -> &[Value] {
+        match self {
+            Value::Composite(c) => c,
+            _ => panic!("expected a composite, got {self:?}"),
+        }
+    }
+
+    fn bits(&self) -> u32 {
+        match self {
+            Value::Bool(b) => *b as u32,
+            Value::I32(i) => *i as u32,
+            Value::U32(u) => *u,
+            Value::F32(f) => f.to_bits(),
+            _ => panic!("expected a scalar, got {self:?}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Pointer {
+    root: Root,
+    /// Component indices from the root to the pointee.
+    path: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Root {
+    Global(usize),
+    /// Local variables of every function on the call stack live in one `Vec`, so pointers to them
+    /// can be passed down to callees.
+    Stack(usize),
+}
+
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Option<Value>),
+}
+
+struct Interpreter<'a> {
+    module: &'a Module,
+    globals: Vec<Value>,
+    /// Which globals have been stored to.
+    stored: Vec<bool>,
+    stack: Vec<Value>,
+}
+
+struct Frame<'a> {
+    function: &'a Function,
+    args: Vec<Value>,
+    /// Index of the first local variable in [`Interpreter::stack`].
+    base: usize,
+    /// Values of the emitted expressions and call results.
+    exprs: Vec<Option<Value>>,
+}
+
+impl<'a> Interpreter<'a> {
+    fn call(&mut self, function: &'a Function, args: Vec<Value>) -> Option<Value> {
+        let base = self.stack.len();
+        for (_, local) in function.local_variables.iter() {
+            let value = match local.init {
+                Some(init) => constant(self.module, init),
+                None => zero(self.module, local.ty),
+            };
+            self.stack.push(value);
+        }
+
+        let mut frame = Frame {
+            function,
+            args,
+            base,
+            exprs: vec![None; function.expressions.len()],
+        };
+        let result = match self.block(&mut frame, &function.body) {
+            Flow::Return(value) => value,
+            _ => None,
+        };
+        self.stack.truncate(base);
+        result
+    }
+
+    fn block(&mut self, frame: &mut Frame<'a>, block: &'a Block) -> Flow {
+        for statement in block {
+            match statement {
+                Statement::Emit(range) => {
+                    for h in range.clone() {
+                        let value = self.eval(frame, h);
+                        frame.exprs[h.index()] = Some(value);
+                    }
+                }
+                Statement::Block(block) => match self.block(frame, block) {
+                    Flow::Next => (),
+                    flow => return flow,
+                },
+                Statement::If {
+                    condition,
+                    accept,
+                    reject,
+                } => {
+                    let block = if self.get(frame, *condition).as_bool() {
+                        accept
+                    } else {
+                        reject
+                    };
+                    match self.block(frame, block) {
+                        Flow::Next => (),
+                        flow => return flow,
+                    }
+                }
+                Statement::Switch { selector, cases } => {
+                    let selector = self.get(frame, *selector).bits();
+                    let start = cases
+                        .iter()
+                        .position(|case| match case.value {
+                            SwitchValue::I32(i) => i as u32 == selector,
+                            SwitchValue::U32(u) => u == selector,
+                            SwitchValue::Default => false,
+                        })
+                        .or_else(|| {
+                            cases
+                                .iter()
+                                .position(|case| matches!(case.value, SwitchValue::Default))
+                        });
+                    if let Some(start) = start {
+                        for case in &cases[start..] {
+                            match self.block(frame, &case.body) {
+                                Flow::Next if case.fall_through => (),
+                                // `break` inside a switch leaves the switch.
+                                Flow::Next | Flow::Break => break,
+                                flow => return flow,
+                            }
+                        }
+                    }
+                }
+                Statement::Loop {
+                    body,
+                    continuing,
+                    break_if,
+                } => loop {
+                    match self.block(frame, body) {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Flow::Return(value),
+                        Flow::Next | Flow::Continue => (),
+                    }
+                    self.block(frame, continuing);
+                    if let Some(condition) = break_if {
+                        if self.get(frame, *condition).as_bool() {
+                            break;
+                        }
+                    }
+                },
+                Statement::Break => return Flow::Break,
+                Statement::Continue => return Flow::Continue,
+                Statement::Return { value } => {
+                    return Flow::Return(value.map(|value| self.get(frame, value)))
+                }
+                // There is only one invocation.
+                Statement::Barrier(_) => (),
+                Statement::Store { pointer, value } => {
+                    let pointer = self.get(frame, *pointer);
+                    let value = self.get(frame, *value);
+                    *self.deref_mut(pointer.as_pointer()) = value;
+                }
+                Statement::Call {
+                    function,
+                    arguments,
+                    result,
+                } => {
+                    let args = arguments.iter().map(|arg| self.get(frame, *arg)).collect();
+                    let module = self.module;
+                    let value = self.call(&module.functions[*function], args);
+                    if let Some(result) = result {
+                        frame.exprs[result.index()] = value;
+                    }
+                }
+                _ => panic!("unsupported statement {statement:?}"),
+            }
+        }
+        Flow::Next
+    }
+
+    fn get(&self, frame: &Frame<'a>, h: Handle<Expression>) -> Value {
+        match &frame.exprs[h.index()] {
+            Some(value) => value.clone(),
+            None => self.eval(frame, h),
+        }
+    }
+
+    fn eval(&self, frame: &Frame<'a>, h: Handle<Expression>) -> Value {
+        match &frame.function.expressions[h] {
+            Expression::Access { base, index } => {
+                let index = self.get(frame, *index).as_index();
+                access(self.get(frame, *base), index)
+            }
+            Expression::AccessIndex { base, index } => {
+                access(self.get(frame, *base), *index as usize)
+            }
+            Expression::Constant(c) => constant(self.module, *c),
+            Expression::Splat { size, value } => {
+                Value::Composite(vec![self.get(frame, *value); *size as usize])
+            }
+            Expression::Swizzle {
+                size,
+                vector,
+                pattern,
+            } => {
+                let vector = self.get(frame, *vector);
+                let components = vector.components();
+                Value::Composite(
+                    pattern[..*size as usize]
+                        .iter()
+                        .map(|c| components[*c as usize].clone())
+                        .collect(),
+                )
+            }
+            Expression::Compose { ty, components } => {
+                let components = components.iter().map(|c| self.get(frame, *c));
+                match self.module.types[*ty].inner {
+                    // `vec4(v.xyz, 1.0)` and friends.
+                    TypeInner::Vector { .. } => Value::Composite(
+                        components
+                            .flat_map(|c| match c {
+                                Value::Composite(c) => c,
+                                scalar => vec![scalar],
+                            })
+                            .collect(),
+                    ),
+                    _ => Value::Composite(components.collect()),
+                }
+            }
+            Expression::FunctionArgument(i) => frame.args[*i as usize].clone(),
+            Expression::GlobalVariable(g) => Value::Pointer(Pointer {
+                root: Root::Global(g.index()),
+                path: Vec::new(),
+            }),
+            Expression::LocalVariable(l) => Value::Pointer(Pointer {
+                root: Root::Stack(frame.base + l.index()),
+                path: Vec::new(),
+            }),
+            Expression::Load { pointer } => {
+                let pointer = self.get(frame, *pointer);
+                self.deref(pointer.as_pointer()).clone()
+            }
+            Expression::Unary { op, expr } => map(self.get(frame, *expr), &|v| unary(*op, v)),
+            Expression::Binary { op, left, right } => {
+                binary(*op, self.get(frame, *left), self.get(frame, *right))
+            }
+            Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                let condition = self.get(frame, *condition);
+                let accept = self.get(frame, *accept);
+                let reject = self.get(frame, *reject);
+                match condition {
+                    Value::Bool(c) => {
+                        if c {
+                            accept
+                        } else {
+                            reject
+                        }
+                    }
+                    Value::Composite(c) => Value::Composite(
+                        c.iter()
+                            .zip(accept.components().iter().zip(reject.components()))
+                            .map(|(c, (a, r))| if c.as_bool() { a.clone() } else { r.clone() })
+                            .collect(),
+                    ),
+                    _ => panic!("invalid select condition {condition:?}"),
+                }
+            }
+            Expression::Relational { fun, argument } => {
+                relational(*fun, self.get(frame, *argument))
+            }
+            Expression::Math {
+                fun,
+                arg,
+                arg1,
+                arg2,
+                arg3,
+            } => {
+                let args = [Some(*arg), *arg1, *arg2, *arg3]
+                    .into_iter()
+                    .flatten()
+                    .map(|arg| self.get(frame, arg))
+                    .collect::<Vec<_>>();
+                math(*fun, &args)
+            }
+            Expression::As {
+                expr,
+                kind,
+                convert,
+            } => map(self.get(frame, *expr), &|v| {
+                cast(v, *kind, convert.is_some())
+            }),
+            Expression::ArrayLength(expr) => {
+                let pointer = self.get(frame, *expr);
+                Value::U32(self.deref(pointer.as_pointer()).components().len() as u32)
+            }
+            Expression::CallResult(_) => panic!("call result used before the call"),
+            expression => panic!("unsupported expression {expression:?}"),
+        }
+    }
+
+    fn deref(&self, pointer: &Pointer) -> &Value {
+        let mut value = match pointer.root {
+            Root::Global(i) => &self.globals[i],
+            Root::Stack(i) => &self.stack[i],
+        };
+        for i in &pointer.path {
+            value = &value.components()[*i];
+        }
+        value
+    }
+
+    fn deref_mut(&mut self, pointer: &Pointer) -> &mut Value {
+        let mut value = match pointer.root {
+            Root::Global(i) => {
+                self.stored[i] = true;
+                &mut self.globals[i]
+            }
+            Root::Stack(i) => &mut self.stack[i],
+        };
+        for i in &pointer.path {
+            value = match value {
+                Value::Composite(c) => &mut c[*i],
+                _ => panic!("expected a composite, got {value:?}"),
+            };
+        }
+        value
+    }
+}
+
+fn access(base: Value, index: usize) -> Value {
+    match base {
+        Value::Pointer(mut pointer) => {
+            pointer.path.push(index);
+            Value::Pointer(pointer)
+        }
+        Value::Composite(mut c) => c.swap_remove(index),
+        _ => panic!("can't index {base:?}"),
+    }
+}
+
+/// Applies `f` to every component of a vector, or to a scalar.
+fn map(value: Value, f: &impl Fn(Value) -> Value) -> Value {
+    match value {
+        Value::Composite(c) => Value::Composite(c.into_iter().map(f).collect()),
+        scalar => f(scalar),
+    }
+}
+
+/// Applies `f` component wise to vectors, scalars are used for every component.
+fn zip(args: &[Value], f: &impl Fn(&[Value]) -> Value) -> Value {
+    let len = args.iter().find_map(|arg| match arg {
+        Value::Composite(c) => Some(c.len()),
+        _ => None,
+    });
+    match len {
+        Some(len) => Value::Composite(
+            (0..len)
+                .map(|i| {
+                    let components = args
+                        .iter()
+                        .map(|arg| match arg {
+                            Value::Composite(c) => c[i].clone(),
+                            scalar => scalar.clone(),
+                        })
+                        .collect::<Vec<_>>();
+                    f(&components)
+                })
+                .collect(),
+        ),
+        None => f(args),
+    }
+}
+
+fn unary(op: UnaryOperator, value: Value) -> Value {
+    match (op, value) {
+        (UnaryOperator::Negate, Value::F32(f)) => Value::F32(-f),
+        (UnaryOperator::Negate, Value::I32(i)) => Value::I32(i.wrapping_neg()),
+        (UnaryOperator::Not, Value::Bool(b)) => Value::Bool(!b),
+        (UnaryOperator::Not, Value::I32(i)) => Value::I32(!i),
+        (UnaryOperator::Not, Value::U32(u)) => Value::U32(!u),
+        (op, value) => panic!("unsupported {op:?} of {value:?}"),
+    }
+}
+
+macro_rules! int_binary {
+    ($variant:ident, $op:expr, $a:expr, $b:expr) => {{
+        let (a, b) = ($a, $b);
+        match $op {
+            BinaryOperator::Add => Value::$variant(a.wrapping_add(b)),
+            BinaryOperator::Subtract => Value::$variant(a.wrapping_sub(b)),
+            BinaryOperator::Multiply => Value::$variant(a.wrapping_mul(b)),
+            // Division by zero gives `a` and a remainder of zero in WGSL.
+            BinaryOperator::Divide => Value::$variant(a.checked_div(b).unwrap_or(a)),
+            BinaryOperator::Modulo => Value::$variant(a.checked_rem(b).unwrap_or(0)),
+            BinaryOperator::Equal => Value::Bool(a == b),
+            BinaryOperator::NotEqual => Value::Bool(a != b),
+            BinaryOperator::Less => Value::Bool(a < b),
+            BinaryOperator::LessEqual => Value::Bool(a <= b),
+            BinaryOperator::Greater => Value::Bool(a > b),
+            BinaryOperator::GreaterEqual => Value::Bool(a >= b),
+            BinaryOperator::And => Value::$variant(a & b),
+            BinaryOperator::ExclusiveOr => Value::$variant(a ^ b),
+            BinaryOperator::InclusiveOr => Value::$variant(a | b),
+            BinaryOperator::ShiftLeft => Value::$variant(a.wrapping_shl(b as u32)),
+            BinaryOperator::ShiftRight => Value::$variant(a.wrapping_shr(b as u32)),
+            op => panic!("unsupported {op:?} of integers"),
+        }
+    }};
+}
+
+fn binary(op: BinaryOperator, left: Value, right: Value) -> Value {
+    match (left, right) {
+        (Value::Composite(l), Value::Composite(r)) => Value::Composite(
+            l.into_iter()
+                .zip(r)
+                .map(|(l, r)| binary(op, l, r))
+                .collect(),
+        ),
+        (Value::Composite(l), r) => {
+            Value::Composite(l.into_iter().map(|l| binary(op, l, r.clone())).collect())
+        }
+        (l, Value::Composite(r)) => {
+            Value::Composite(r.into_iter().map(|r| binary(op, l.clone(), r)).collect())
+        }
+        (Value::F32(a), Value::F32(b)) => match op {
+            BinaryOperator::Add => Value::F32(a + b),
+            BinaryOperator::Subtract => Value::F32(a - b),
+            BinaryOperator::Multiply => Value::F32(a * b),
+            BinaryOperator::Divide => Value::F32(a / b),
+            BinaryOperator::Modulo => Value::F32(a % b),
+            BinaryOperator::Equal => Value::Bool(a == b),
+            BinaryOperator::NotEqual => Value::Bool(a != b),
+            BinaryOperator::Less => Value::Bool(a < b),
+            BinaryOperator::LessEqual => Value::Bool(a <= b),
+            BinaryOperator::Greater => Value::Bool(a > b),
+            BinaryOperator::GreaterEqual => Value::Bool(a >= b),
+            op => panic!("unsupported {op:?} of floats"),
+        },
+        (Value::U32(a), Value::U32(b)) => int_binary!(U32, op, a, b),
+        (Value::I32(a), Value::I32(b)) => int_binary!(I32, op, a, b),
+        // The shift amount is always unsigned.
+        (Value::I32(a), Value::U32(b)) => int_binary!(I32, op, a, b as i32),
+        (Value::Bool(a), Value::Bool(b)) => match op {
+            BinaryOperator::Equal => Value::Bool(a == b),
+            BinaryOperator::NotEqual => Value::Bool(a != b),
+            BinaryOperator::And | BinaryOperator::LogicalAnd => Value::Bool(a & b),
+            BinaryOperator::InclusiveOr | BinaryOperator::LogicalOr => Value::Bool(a | b),
+            BinaryOperator::ExclusiveOr => Value::Bool(a ^ b),
+            op => panic!("unsupported {op:?} of bools"),
+        },
+        (l, r) => panic!("unsupported {op:?} of {l:?} and {r:?}"),
+    }
+}
+
+fn relational(fun: RelationalFunction, argument: Value) -> Value {
+    match fun {
+        RelationalFunction::All => Value::Bool(argument.components().iter().all(|c| c.as_bool())),
+        RelationalFunction::Any => Value::Bool(argument.components().iter().any(|c| c.as_bool())),
+        fun => map(argument, &|v| match v {
+            Value::F32(f) => Value::Bool(match fun {
+                RelationalFunction::IsNan => f.is_nan(),
+                RelationalFunction::IsInf => f.is_infinite(),
+                RelationalFunction::IsFinite => f.is_finite(),
+                _ => f.is_normal(),
+            }),
+            v => panic!("unsupported {fun:?} of {v:?}"),
+        }),
+    }
+}
+
+fn math(fun: MathFunction, args: &[Value]) -> Value {
+    match fun {
+        MathFunction::Dot => dot(&args[0], &args[1]),
+        MathFunction::Cross => {
+            // Same operation order as glam.
+            let a = args[0].components();
+            let b = args[1].components();
+            let sub_mul = |i: usize, j: usize| {
+                binary(
+                    BinaryOperator::Subtract,
+                    binary(BinaryOperator::Multiply, a[i].clone(), b[j].clone()),
+                    binary(BinaryOperator::Multiply, b[i].clone(), a[j].clone()),
+                )
+            };
+            Value::Composite(vec![sub_mul(1, 2), sub_mul(2, 0), sub_mul(0, 1)])
+        }
+        MathFunction::Length => length(&args[0]),
+        MathFunction::Distance => length(&binary(
+            BinaryOperator::Subtract,
+            args[0].clone(),
+            args[1].clone(),
+        )),
+        MathFunction::Normalize => {
+            let recip = match length(&args[0]) {
+                Value::F32(l) => Value::F32(1.0 / l),
+                l => panic!("unsupported length {l:?}"),
+            };
+            binary(BinaryOperator::Multiply, args[0].clone(), recip)
+        }
+        fun => zip(args, &|args| scalar_math(fun, args)),
+    }
+}
+
+fn dot(a: &Value, b: &Value) -> Value {
+    a.components()
+        .iter()
+        .zip(b.components())
+        .map(|(a, b)| binary(BinaryOperator::Multiply, a.clone(), b.clone()))
+        .reduce(|sum, x| binary(BinaryOperator::Add, sum, x))
+        .unwrap()
+}
+
+fn length(v: &Value) -> Value {
+    match v {
+        Value::F32(f) => Value::F32(f.abs()),
+        v => match dot(v, v) {
+            Value::F32(d) => Value::F32(d.sqrt()),
+            d => panic!("unsupported length of {d:?}"),
+        },
+    }
+}
+
+fn scalar_math(fun: MathFunction, args: &[Value]) -> Value {
+    use MathFunction as M;
+    match args {
+        [Value::F32(x), rest @ ..] => {
+            let arg = |i: usize| match rest[i - 1] {
+                Value::F32(f) => f,
+                ref v => panic!("expected a float, got {v:?}"),
+            };
+            let x = *x;
+            Value::F32(match fun {
+                M::Abs => x.abs(),
+                M::Min => x.min(arg(1)),
+                M::Max => x.max(arg(1)),
+                M::Clamp => x.max(arg(1)).min(arg(2)),
+                M::Saturate => x.clamp(0.0, 1.0),
+                M::Cos => x.cos(),
+                M::Cosh => x.cosh(),
+                M::Sin => x.sin(),
+                M::Sinh => x.sinh(),
+                M::Tan => x.tan(),
+                M::Tanh => x.tanh(),
+                M::Acos => x.acos(),
+                M::Asin => x.asin(),
+                M::Atan => x.atan(),
+                M::Atan2 => x.atan2(arg(1)),
+                M::Asinh => x.asinh(),
+                M::Acosh => x.acosh(),
+                M::Atanh => x.atanh(),
+                M::Radians => x.to_radians(),
+                M::Degrees => x.to_degrees(),
+                M::Ceil => x.ceil(),
+                M::Floor => x.floor(),
+                // Half way cases round to even.
+                M::Round => {
+                    if (x - x.trunc()).abs() == 0.5 {
+                        2.0 * (x * 0.5).round()
+                    } else {
+                        x.round()
+                    }
+                }
+                M::Fract => x - x.floor(),
+                M::Trunc => x.trunc(),
+                M::Exp => x.exp(),
+                M::Exp2 => x.exp2(),
+                M::Log => x.ln(),
+                M::Log2 => x.log2(),
+                M::Pow => x.powf(arg(1)),
+                M::Sign => {
+                    if x == 0.0 {
+                        0.0
+                    } else {
+                        x.signum()
+                    }
+                }
+                M::Fma => x.mul_add(arg(1), arg(2)),
+                M::Mix => x * (1.0 - arg(2)) + arg(1) * arg(2),
+                M::Step => {
+                    if arg(1) >= x {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                }
+                M::SmoothStep => {
+                    let t = ((arg(2) - x) / (arg(1) - x)).clamp(0.0, 1.0);
+                    t * t * (3.0 - 2.0 * t)
+                }
+                M::Sqrt => x.sqrt(),
+                M::InverseSqrt => 1.0 / x.sqrt(),
+                fun => panic!("unsupported {fun:?} of floats"),
+            })
+        }
+        [Value::U32(x), rest @ ..] => {
+            let arg = |i: usize| rest[i - 1].bits();
+            let x = *x;
+            Value::U32(match fun {
+                M::Abs => x,
+                M::Min => x.min(arg(1)),
+                M::Max => x.max(arg(1)),
+                M::Clamp => x.max(arg(1)).min(arg(2)),
+                M::CountTrailingZeros => x.trailing_zeros(),
+                M::CountLeadingZeros => x.leading_zeros(),
+                M::CountOneBits => x.count_ones(),
+                M::ReverseBits => x.reverse_bits(),
+                M::FindLsb if x == 0 => u32::MAX,
+                M::FindLsb => x.trailing_zeros(),
+                M::FindMsb if x == 0 => u32::MAX,
+                M::FindMsb => 31 - x.leading_zeros(),
+                fun => panic!("unsupported {fun:?} of unsigned integers"),
+            })
+        }
+        [Value::I32(x), rest @ ..] => {
+            let arg = |i: usize| rest[i - 1].bits() as i32;
+            let x = *x;
+            Value::I32(match fun {
+                M::Abs => x.wrapping_abs(),
+                M::Min => x.min(arg(1)),
+                M::Max => x.max(arg(1)),
+                M::Clamp => x.max(arg(1)).min(arg(2)),
+                M::Sign => x.signum(),
+                M::CountTrailingZeros => x.trailing_zeros() as i32,
+                M::CountLeadingZeros => x.leading_zeros() as i32,
+                M::CountOneBits => x.count_ones() as i32,
+                M::ReverseBits => x.reverse_bits(),
+                fun => panic!("unsupported {fun:?} of signed integers"),
+            })
+        }
+        args => panic!("unsupported {fun:?} of {args:?}"),
+    }
+}
+
+/// `As` with `convert`, otherwise a bitcast.
+fn cast(value: Value, kind: ScalarKind, convert: bool) -> Value {
+    if !convert {
+        let bits = value.bits();
+        return match kind {
+            ScalarKind::Sint => Value::I32(bits as i32),
+            ScalarKind::Uint => Value::U32(bits),
+            ScalarKind::Float => Value::F32(f32::from_bits(bits)),
+            ScalarKind::Bool => Value::Bool(bits != 0),
+        };
+    }
+    match (value, kind) {
+        (Value::F32(f), ScalarKind::Sint) => Value::I32(f as i32),
+        (Value::F32(f), ScalarKind::Uint) => Value::U32(f as u32),
+        (Value::F32(f), ScalarKind::Float) => Value::F32(f),
+        (Value::F32(f), ScalarKind::Bool) => Value::Bool(f != 0.0),
+        (Value::I32(i), ScalarKind::Float) => Value::F32(i as f32),
+        (Value::U32(u), ScalarKind::Float) => Value::F32(u as f32),
+        (Value::Bool(b), ScalarKind::Float) => Value::F32(b as u32 as f32),
+        (value, kind) => cast(value, kind, false),
+    }
+}
+
+fn constant(module: &Module, h: Handle<naga::Constant>) -> Value {
+    match &module.constants[h].inner {
+        ConstantInner::Scalar { value, .. } => match *value {
+            ScalarValue::Sint(i) => Value::I32(i as i32),
+            ScalarValue::Uint(u) => Value::U32(u as u32),
+            ScalarValue::Float(f) => Value::F32(f as f32),
+            ScalarValue::Bool(b) => Value::Bool(b),
+        },
+        ConstantInner::Composite { components, .. } => {
+            Value::Composite(components.iter().map(|c| constant(module, *c)).collect())
+        }
+    }
+}
+
+fn zero_scalar(kind: ScalarKind) -> Value {
+    match kind {
+        ScalarKind::Sint => Value::I32(0),
+        ScalarKind::Uint => Value::U32(0),
+        ScalarKind::Float => Value::F32(0.0),
+        ScalarKind::Bool => Value::Bool(false),
+    }
+}
+
+fn array_len(module: &Module, size: naga::ArraySize, bytes: usize, stride: u32) -> usize {
+    match size {
+        naga::ArraySize::Constant(c) => constant(module, c).as_index(),
+        naga::ArraySize::Dynamic => bytes / stride as usize,
+    }
+}
+
+fn zero(module: &Module, ty: Handle<Type>) -> Value {
+    match module.types[ty].inner {
+        TypeInner::Scalar { kind, .. } | TypeInner::Atomic { kind, .. } => zero_scalar(kind),
+        TypeInner::Vector { size, kind, .. } => {
+            Value::Composite(vec![zero_scalar(kind); size as usize])
+        }
+        TypeInner::Array { base, size, stride } => {
+            let len = array_len(module, size, 0, stride);
+            Value::Composite(vec![zero(module, base); len])
+        }
+        TypeInner::Struct { ref members, .. } => {
+            Value::Composite(members.iter().map(|m| zero(module, m.ty)).collect())
+        }
+        ref inner => panic!("unsupported type {inner:?}"),
+    }
+}
+
+fn read_scalar(kind: ScalarKind, bytes: &[u8]) -> Value {
+    let bits = u32::from_le_bytes(bytes[..4].try_into().unwrap());
+    cast(Value::U32(bits), kind, false)
+}
+
+/// Reads a value of type `ty` from buffer contents, using the offsets and strides of the type.
+fn read(module: &Module, ty: Handle<Type>, bytes: &[u8]) -> Value {
+    match module.types[ty].inner {
+        TypeInner::Scalar { kind, .. } | TypeInner::Atomic { kind, .. } => read_scalar(kind, bytes),
+        TypeInner::Vector { size, kind, width } => Value::Composite(
+            (0..size as usize)
+                .map(|i| read_scalar(kind, &bytes[i * width as usize..]))
+                .collect(),
+        ),
+        TypeInner::Array { base, size, stride } => {
+            let len = array_len(module, size, bytes.len(), stride);
+            Value::Composite(
+                (0..len)
+                    .map(|i| read(module, base, &bytes[i * stride as usize..]))
+                    .collect(),
+            )
+        }
+        TypeInner::Struct { ref members, .. } => Value::Composite(
+            members
+                .iter()
+                .map(|m| read(module, m.ty, &bytes[m.offset as usize..]))
+                .collect(),
+        ),
+        ref inner => panic!("unsupported buffer type {inner:?}"),
+    }
+}
+
+/// The inverse of [`read`].
+fn write(module: &Module, ty: Handle<Type>, value: &Value, bytes: &mut [u8]) {
+    match module.types[ty].inner {
+        TypeInner::Scalar { .. } | TypeInner::Atomic { .. } => {
+            bytes[..4].copy_from_slice(&value.bits().to_le_bytes())
+        }
+        TypeInner::Vector { width, .. } => {
+            for (i, c) in value.components().iter().enumerate() {
+                bytes[i * width as usize..][..4].copy_from_slice(&c.bits().to_le_bytes());
+            }
+        }
+        TypeInner::Array { base, stride, .. } => {
+            for (i, c) in value.components().iter().enumerate() {
+                write(module, base, c, &mut bytes[i * stride as usize..]);
+            }
+        }
+        TypeInner::Struct { ref members, .. } => {
+            for (m, c) in members.iter().zip(value.components()) {
+                write(module, m.ty, c, &mut bytes[m.offset as usize..]);
+            }
+        }
+        ref inner => panic!("unsupported buffer type {inner:?}"),
+    }
+}
@@ -0,0 +1,213 @@
+use rust_gpu::RustGpuConfig;
+use std::borrow::Cow;
+use structopt::StructOpt;
+use wgpu::*;
+
+pub mod codegen;
+mod compute;
+pub mod external;
+pub mod hash;
+pub mod interpreter;
+mod ppm;
+pub mod reflect;
+pub mod roundtrip;
+pub mod rust_gpu;
+pub mod scene;
+pub mod settings;
+pub mod slang;
+pub mod spirv_dump;
+mod timestamp;
+
+struct CompiledShaderModules {
+    named_spv_modules: Vec<(Option<String>, ShaderModuleDescriptor<'static>)>,
+}
+
+fn maybe_watch(
+    config: &RustGpuConfig,
+    on_watch: Option<Box<dyn FnMut(CompiledShaderModules) + Send + 'static>>,
+) -> CompiledShaderModules {
+    let builder = config.builder();
+    let initial_result = if let Some(mut f) = on_watch {
+        builder
+            .watch(move |compile_result| f(handle_compile_result(compile_result)))
+            .expect("Configuration is correct for watching")
+    } else {
+        builder.build().unwrap()
+    };
+    handle_compile_result(initial_result)
+}
+
+/// Builds the shader crate at startup with `--compile-rust-gpu`, otherwise loads the module from
+/// the cache, only building it when the sources or settings changed.
+fn load_rust_gpu(config: &RustGpuConfig, compile: bool) -> CompiledShaderModules {
+    if compile {
+        return maybe_watch(config, None);
+    }
+    let path = config.build_cached().unwrap();
+    CompiledShaderModules {
+        named_spv_modules: vec![(None, load_spv_module(&path))],
+    }
+}
+
+fn load_spv_module(path: &std::path::Path) -> ShaderModuleDescriptor<'static> {
+    let data = std::fs::read(path).unwrap();
+    let spirv = Cow::Owned(util::make_spirv_raw(&data).into_owned());
+    ShaderModuleDescriptor {
+        label: None,
+        source: ShaderSource::SpirV(spirv),
+    }
+}
+
+fn handle_compile_result(compile_result: spirv_builder::CompileResult) -> CompiledShaderModules {
+    CompiledShaderModules {
+        named_spv_modules: match compile_result.module {
+            spirv_builder::ModuleResult::SingleModule(path) => {
+                vec![(None, load_spv_module(&path))]
+            }
+            spirv_builder::ModuleResult::MultiModule(modules) => modules
+                .into_iter()
+                .map(|(name, path)| (Some(name), load_spv_module(&path)))
+                .collect(),
+        },
+    }
+}
+
+#[derive(StructOpt, Clone)]
+#[structopt(name = "example-runner-wgpu")]
+pub struct Options {
+    #[structopt(long, default_value = "512")]
+    size: u32,
+    /// Compile the Slang kernel at startup, into `target/slang-cache`, instead of loading the
+    /// SPIR-V build.rs compiled
+    #[structopt(long)]
+    compile_slang: bool,
+    /// slangc binary used by `--compile-slang`
+    #[structopt(long, parse(from_os_str), default_value = "slangc")]
+    slangc: std::path::PathBuf,
+    /// Profile passed to slangc
+    #[structopt(long, default_value = "sm_5_0")]
+    slang_profile: String,
+    /// slangc optimization level, 0 to 3, slangc's default when not set
+    #[structopt(long, parse(try_from_str = slang::parse_opt_level))]
+    slang_opt: Option<u8>,
+    /// Have slangc emit SPIR-V `direct`ly or through `glsl`, slangc's default when not set
+    #[structopt(long)]
+    slang_emit: Option<slang::SlangEmit>,
+    /// Extra flag passed to slangc, can be repeated
+    #[structopt(long = "slang-flag", number_of_values = 1, allow_hyphen_values = true)]
+    slang_flags: Vec<String>,
+    /// Build the rust-gpu kernel at startup instead of loading the cached SPIR-V
+    #[structopt(long)]
+    compile_rust_gpu: bool,
+    /// Also benchmark the rust-gpu kernel built with other SPIR-T passes, Vulkan targets and
+    /// panic strategies
+    #[structopt(long)]
+    rust_gpu_matrix: bool,
+    /// Also benchmark `main` with its loops written with `for` and `while` in every language, and
+    /// `for in` with rust-gpu, with the SPIR-V size of each
+    #[structopt(long)]
+    loop_forms: bool,
+    /// Also run `main` and a Kahan summation version in every language, and print how far each
+    /// sum is from an f64 reference computed on the CPU
+    #[structopt(long)]
+    precision: bool,
+    /// Also time `sin`, division, `normalize` and the shader crate's `util` functions one at a
+    /// time in every language and on the CPU, with their error against f64
+    #[structopt(long)]
+    micro: bool,
+    /// Also run degenerate triangles, parallel, NaN and infinite rays and hits on edges and
+    /// vertices through every shader, and print where they don't match the CPU bit for bit
+    #[structopt(long)]
+    edge_cases: bool,
+    /// Also time the rust-gpu and Slang SPIR-V handed to the driver as is, instead of being
+    /// translated by naga, on adapters that support it
+    #[structopt(long)]
+    spirv_passthrough: bool,
+    /// Also benchmark the kernel in a `.wgsl`, `.spv` or `.comp` (GLSL) file, can be repeated. It
+    /// gets the bindings of the built-in `main` kernel, the output buffer at binding 0 and the
+    /// settings uniform at binding 1
+    #[structopt(long = "shader", parse(from_os_str), number_of_values = 1)]
+    shaders: Vec<std::path::PathBuf>,
+    /// Entry point of the `--shader` kernels
+    #[structopt(long, default_value = "main")]
+    entry: String,
+    /// Also run each variant translated by naga, rust-gpu and Slang SPIR-V to WGSL and WGSL to
+    /// SPIR-V
+    #[structopt(long)]
+    naga_roundtrip: bool,
+    /// Also run `main_push_constant` of each language, with `settings` in push constants instead
+    /// of the uniform, on adapters that support them, and `main_constant`, with the size known
+    /// when it's compiled. Slang is compiled again for each
+    #[structopt(long)]
+    settings_variants: bool,
+    /// Write each variant's SPIR-V with a disassembly and instruction counts to this directory,
+    /// and print the counts next to the timings
+    #[structopt(long, parse(from_os_str))]
+    dump_shaders: Option<std::path::PathBuf>,
+    /// Fail instead of warning when the Slang SPIR-V wasn't compiled from the current source
+    #[structopt(long)]
+    require_fresh: bool,
+    /// Also benchmark Slang compiled with every optimization level and emit path for a few
+    /// profiles, cached under `target/slang-cache`
+    #[structopt(long)]
+    slang_matrix: bool,
+    /// Also run the BVH traversal benchmark
+    #[structopt(long)]
+    bvh: bool,
+    /// Number of random triangles in the BVH scene
+    #[structopt(long, default_value = "4096")]
+    triangles: u32,
+    /// Build the BVH scene from an OBJ file instead of random triangles
+    #[structopt(long, parse(from_os_str))]
+    obj: Option<std::path::PathBuf>,
+    /// Also run the BVH benchmark with coherent camera rays, rendering a PPM image per variant
+    #[structopt(long)]
+    camera: bool,
+    /// Where the camera PPM images are written
+    #[structopt(long, parse(from_os_str), default_value = ".")]
+    ppm_dir: std::path::PathBuf,
+    /// Also run the default benchmark with this generator: hash, pcg32, xorshift, sobol or r2
+    #[structopt(long, parse(try_from_str = parse_rng))]
+    rng: Option<u32>,
+    /// Draw the `--rng` ray directions uniformly from the sphere instead of normalizing a point in
+    /// the cube, only valid with `--rng`
+    #[structopt(long, requires = "rng")]
+    sphere_rays: bool,
+    /// Check that every generator and `uhash` give the same bits on the GPU as on the CPU
+    #[structopt(long)]
+    rng_parity: bool,
+    /// Run the WGSL, GLSL and Slang kernels in a CPU interpreter instead of on the GPU and check them
+    /// against the CPU result
+    #[structopt(long)]
+    interpret: bool,
+    /// Regenerate the Slang kernel from `compute_shader.wgsl` and exit
+    #[structopt(long)]
+    generate_shaders: bool,
+}
+
+fn parse_rng(name: &str) -> Result<u32, String> {
+    compute_shader::rng::RNG_NAMES
+        .iter()
+        .position(|n| *n == name)
+        .map(|kind| kind as u32)
+        .ok_or_else(|| format!("unknown generator {name:?}"))
+}
+
+/// Where build.rs compiled `entry_point` of the Slang kernel to, missing when slangc wasn't found.
+pub fn slang_spv_path(entry_point: &str) -> std::path::PathBuf {
+    slang::module_path(
+        &std::path::Path::new(env!("OUT_DIR")).join("slang"),
+        entry_point,
+    )
+}
+
+/// Runs whatever `options` asks for, the benchmark by default.
+pub fn run(options: &Options) {
+    if options.generate_shaders {
+        return codegen::write();
+    }
+    if options.interpret {
+        return compute::interpret(options);
+    }
+    compute::start(options);
+}
@@ -0,0 +1,25 @@
+use std::{io::Write, path::Path};
+
+/// Writes a `size * size` image of hit distances as a grayscale binary PPM. Misses (`f32::MAX`)
+/// are black, hits go from white for the nearest to dark gray for the farthest one in the image.
+pub fn write_depth(path: &Path, size: u32, depth: &[f32]) -> std::io::Result<()> {
+    let (near, far) = depth
+        .iter()
+        .filter(|t| **t < f32::MAX)
+        .fold((f32::MAX, 0.0f32), |(near, far), t| {
+            (near.min(*t), far.max(*t))
+        });
+    let range = (far - near).max(f32::EPSILON);
+
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    write!(file, "P6\n{size} {size}\n255\n")?;
+    for t in depth {
+        let v = if *t < f32::MAX {
+            (255.0 - (t - near) / range * 191.0) as u8
+        } else {
+            0
+        };
+        file.write_all(&[v, v, v])?;
+    }
+    file.flush()
+}
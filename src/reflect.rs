@@ -0,0 +1,146 @@
+//! Bind group layouts read from the shader instead of being hardcoded, so a kernel only has to
+//! declare the buffers it uses and a mismatch with the workload is reported before wgpu sees it.
+
+use naga::{
+    valid::{Capabilities, ModuleInfo, ValidationFlags, Validator},
+    AddressSpace, Module, StorageAccess,
+};
+use std::collections::BTreeMap;
+use wgpu::{BindGroupLayoutEntry, BindingType, BufferBindingType, ShaderSource, ShaderStages};
+
+fn parse(source: &ShaderSource, entry_point: &str) -> Result<(Module, ModuleInfo, usize), String> {
+    let module = crate::roundtrip::parse(source)?;
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|err| format!("naga validation failed: {err}"))?;
+    let index = module
+        .entry_points
+        .iter()
+        .position(|ep| ep.name == entry_point)
+        .ok_or_else(|| format!("no entry point named `{entry_point}`"))?;
+    Ok((module, info, index))
+}
+
+/// Buffer type by binding of the group 0 globals `entry_point` uses. Globals aliasing the same
+/// binding, like `data` and `data_u32`, are merged, read-only only if all of them are.
+pub fn bindings(
+    source: &ShaderSource,
+    entry_point: &str,
+) -> Result<BTreeMap<u32, BufferBindingType>, String> {
+    let (module, info, index) = parse(source, entry_point)?;
+    let uses = info.get_entry_point(index);
+
+    let mut bindings = BTreeMap::new();
+    for (handle, global) in module.global_variables.iter() {
+        let Some(binding) = &global.binding else {
+            continue;
+        };
+        if uses[handle].is_empty() {
+            continue;
+        }
+        let name = global.name.as_deref().unwrap_or("?");
+        if binding.group != 0 {
+            return Err(format!(
+                "`{name}` is in group {}, only group 0 is bound",
+                binding.group
+            ));
+        }
+        let ty = match global.space {
+            AddressSpace::Uniform => BufferBindingType::Uniform,
+            AddressSpace::Storage { access } => BufferBindingType::Storage {
+                read_only: !access.contains(StorageAccess::STORE),
+            },
+            space => return Err(format!("`{name}` is a {space:?} binding, not a buffer")),
+        };
+        match (bindings.insert(binding.binding, ty), ty) {
+            (None, _) => (),
+            (
+                Some(BufferBindingType::Storage { read_only: a }),
+                BufferBindingType::Storage { read_only: b },
+            ) => {
+                bindings.insert(
+                    binding.binding,
+                    BufferBindingType::Storage { read_only: a && b },
+                );
+            }
+            (Some(_), _) => {
+                return Err(format!(
+                    "binding {} is declared as both a uniform and a storage buffer",
+                    binding.binding
+                ))
+            }
+        }
+    }
+    Ok(bindings)
+}
+
+/// Size in bytes of the push constants `entry_point` uses, 0 if it doesn't use any.
+pub fn push_constant_size(source: &ShaderSource, entry_point: &str) -> Result<u32, String> {
+    let (module, info, index) = parse(source, entry_point)?;
+    let uses = info.get_entry_point(index);
+    Ok(module
+        .global_variables
+        .iter()
+        .filter(|(handle, global)| {
+            global.space == AddressSpace::PushConstant && !uses[*handle].is_empty()
+        })
+        .map(|(_, global)| module.types[global.ty].inner.size(&module.constants))
+        .sum())
+}
+
+fn describe(ty: BufferBindingType) -> &'static str {
+    match ty {
+        BufferBindingType::Uniform => "a uniform",
+        BufferBindingType::Storage { read_only: true } => "a read-only storage buffer",
+        BufferBindingType::Storage { read_only: false } => "a read-write storage buffer",
+    }
+}
+
+/// Checks the shader's `bindings` against the buffers the workload `provides`. Buffers the shader
+/// doesn't use are fine, they're just left out of the bind group.
+pub fn check(
+    bindings: &BTreeMap<u32, BufferBindingType>,
+    provides: &BTreeMap<u32, BufferBindingType>,
+) -> Result<(), String> {
+    for (binding, ty) in bindings {
+        let Some(provided) = provides.get(binding) else {
+            return Err(format!(
+                "binding {binding} is {}, but the workload only provides bindings {:?}",
+                describe(*ty),
+                provides.keys().collect::<Vec<_>>()
+            ));
+        };
+        let compatible = match (ty, provided) {
+            (BufferBindingType::Uniform, BufferBindingType::Uniform) => true,
+            (
+                BufferBindingType::Storage { read_only },
+                BufferBindingType::Storage { read_only: r },
+            ) => *read_only || !r,
+            _ => false,
+        };
+        if !compatible {
+            return Err(format!(
+                "binding {binding} is {} in the shader, but the workload provides {}",
+                describe(*ty),
+                describe(*provided)
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn layout_entries(bindings: &BTreeMap<u32, BufferBindingType>) -> Vec<BindGroupLayoutEntry> {
+    bindings
+        .iter()
+        .map(|(binding, ty)| BindGroupLayoutEntry {
+            binding: *binding,
+            count: None,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                has_dynamic_offset: false,
+                min_binding_size: None,
+                ty: *ty,
+            },
+        })
+        .collect()
+}
@@ -0,0 +1,83 @@
+//! Shader modules translated by naga into the other language, so differences between the source
+//! languages can be told apart from differences in the SPIR-V the driver ends up with.
+
+use naga::{
+    valid::{Capabilities, ModuleInfo, ValidationFlags, Validator},
+    Module,
+};
+use std::borrow::Cow;
+use wgpu::ShaderSource;
+
+pub(crate) fn validate(module: &Module) -> Result<ModuleInfo, String> {
+    Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(module)
+        .map_err(|err| format!("naga validation failed: {err}"))
+}
+
+pub fn module_to_spirv(module: &Module) -> Result<Vec<u32>, String> {
+    naga::back::spv::write_vec(module, &validate(module)?, &Default::default(), None)
+        .map_err(|err| format!("naga couldn't write spir-v: {err}"))
+}
+
+/// Reads any of the shader sources the benchmark creates into a naga module.
+pub fn parse(source: &ShaderSource) -> Result<Module, String> {
+    match source {
+        ShaderSource::SpirV(words) => {
+            naga::front::spv::parse_u8_slice(bytemuck::cast_slice(words), &Default::default())
+                .map_err(|err| format!("naga couldn't read spir-v: {err}"))
+        }
+        ShaderSource::Wgsl(wgsl) => {
+            naga::front::wgsl::parse_str(wgsl).map_err(|err| err.emit_to_string(wgsl))
+        }
+        ShaderSource::Glsl {
+            shader,
+            stage,
+            defines,
+        } => {
+            let options = naga::front::glsl::Options {
+                stage: *stage,
+                defines: defines.clone(),
+            };
+            naga::front::glsl::Frontend::default()
+                .parse(&options, shader)
+                .map_err(|errors| format!("naga couldn't read glsl: {errors:?}"))
+        }
+        _ => Err("unsupported shader source".to_string()),
+    }
+}
+
+pub fn wgsl_to_spirv(wgsl: &str) -> Result<Vec<u32>, String> {
+    module_to_spirv(&parse(&ShaderSource::Wgsl(Cow::Borrowed(wgsl)))?)
+}
+
+pub fn module_to_wgsl(module: &Module) -> Result<String, String> {
+    naga::back::wgsl::write_string(
+        module,
+        &validate(module)?,
+        naga::back::wgsl::WriterFlags::empty(),
+    )
+    .map_err(|err| format!("naga couldn't write wgsl: {err}"))
+}
+
+pub fn spirv_to_wgsl(words: &[u32]) -> Result<String, String> {
+    module_to_wgsl(&parse(&ShaderSource::SpirV(Cow::Borrowed(words)))?)
+}
+
+/// SPIR-V to WGSL, or WGSL and GLSL to SPIR-V.
+pub fn translate(source: &ShaderSource) -> Result<ShaderSource<'static>, String> {
+    match source {
+        ShaderSource::SpirV(words) => Ok(ShaderSource::Wgsl(Cow::Owned(spirv_to_wgsl(words)?))),
+        ShaderSource::Wgsl(wgsl) => Ok(ShaderSource::SpirV(Cow::Owned(wgsl_to_spirv(wgsl)?))),
+        _ => Ok(ShaderSource::SpirV(Cow::Owned(module_to_spirv(&parse(
+            source,
+        )?)?))),
+    }
+}
+
+/// `"<name> -> wgsl"` or `"<name> -> spv"`, like the parity tests name their round trips.
+pub fn name(name: &str, source: &ShaderSource) -> String {
+    match source {
+        ShaderSource::SpirV(_) => format!("{name} -> wgsl"),
+        _ => format!("{name} -> spv"),
+    }
+}
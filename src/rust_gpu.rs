@@ -0,0 +1,179 @@
+use crate::hash::fnv1a;
+use spirv_builder::{MetadataPrintout, ShaderPanicStrategy, SpirvBuilder, SpirvBuilderError};
+use std::path::{Path, PathBuf};
+
+/// SPIR-T passes run by the default configuration.
+const DEFAULT_PASSES: &[&str] = &["reduce", "fuse_selects"];
+
+/// The shader crate of `main_push_constant`, see [`crate::settings::Settings::PushConstant`].
+pub const PUSH_CONSTANT_CRATE: &str = "compute_shader_push_constant";
+
+/// rust-gpu codegen settings for building the shader crate.
+#[derive(Clone, Debug)]
+pub struct RustGpuConfig {
+    pub name: String,
+    /// Crate in `shaders` to build
+    pub shader_crate: &'static str,
+    pub target: &'static str,
+    /// SPIR-T passes to run, none when empty
+    pub spirt_passes: &'static [&'static str],
+    pub panic_strategy: ShaderPanicStrategy,
+    /// Dump the module after each SPIR-T pass to `spirt-passes`
+    pub dump_spirt_passes: bool,
+}
+
+impl Default for RustGpuConfig {
+    fn default() -> Self {
+        Self {
+            name: "rust-gpu".to_string(),
+            shader_crate: "compute_shader",
+            target: "spirv-unknown-vulkan1.1",
+            spirt_passes: DEFAULT_PASSES,
+            panic_strategy: ShaderPanicStrategy::SilentExit,
+            dump_spirt_passes: true,
+        }
+    }
+}
+
+impl RustGpuConfig {
+    pub fn codegen_args(&self) -> String {
+        let mut args = Vec::new();
+        if self.dump_spirt_passes {
+            args.push("--dump-spirt-passes=$PWD/spirt-passes".to_string());
+        }
+        if !self.spirt_passes.is_empty() {
+            args.push(format!("--spirt-passes={}", self.spirt_passes.join(",")));
+        }
+        args.join(" ")
+    }
+
+    /// Codegen args are read from the environment by the builder, so this sets
+    /// `RUSTGPU_CODEGEN_ARGS` for the whole process.
+    pub fn builder(&self) -> SpirvBuilder {
+        std::env::set_var("RUSTGPU_CODEGEN_ARGS", self.codegen_args());
+
+        let crate_path = [env!("CARGO_MANIFEST_DIR"), "shaders", self.shader_crate]
+            .iter()
+            .copied()
+            .collect::<PathBuf>();
+
+        SpirvBuilder::new(crate_path, self.target)
+            .print_metadata(MetadataPrintout::None)
+            .shader_panic_strategy(self.panic_strategy)
+    }
+}
+
+/// Everything that goes into the shader module besides the settings: the shader crates and the
+/// toolchain and spirv-builder versions.
+fn source_files() -> Vec<PathBuf> {
+    fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+        for entry in std::fs::read_dir(dir).expect("unable to read shader crates") {
+            let path = entry.expect("unable to read shader crates").path();
+            if path.is_dir() {
+                if path.file_name() != Some("target".as_ref()) {
+                    walk(&path, files);
+                }
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut files = vec![root.join("Cargo.toml"), root.join("rust-toolchain.toml")];
+    walk(&root.join("shaders"), &mut files);
+    files.sort();
+    files
+}
+
+fn cache_dir() -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "target", "rust-gpu-cache"]
+        .iter()
+        .collect()
+}
+
+impl RustGpuConfig {
+    /// Hash of the shader sources and of every setting that changes the module.
+    pub fn cache_key(&self) -> u64 {
+        let mut key = Vec::new();
+        for path in source_files() {
+            key.extend(path.to_string_lossy().bytes());
+            key.push(0);
+            key.extend(std::fs::read(&path).unwrap_or_default());
+            key.push(0);
+        }
+        // The dump doesn't change the module
+        let settings = RustGpuConfig {
+            name: String::new(),
+            dump_spirt_passes: false,
+            ..self.clone()
+        };
+        key.extend(format!("{settings:?} {}", settings.codegen_args()).bytes());
+        fnv1a(&key)
+    }
+
+    /// Builds the shader crate unless a module with the same [`Self::cache_key`] is already in
+    /// `target/rust-gpu-cache`, returns the path of the module.
+    pub fn build_cached(&self) -> Result<PathBuf, SpirvBuilderError> {
+        let path = cache_dir().join(format!("{:016x}.spv", self.cache_key()));
+        if !path.exists() {
+            let result = self.builder().build()?;
+            std::fs::create_dir_all(cache_dir()).expect("unable to create the rust-gpu cache");
+            std::fs::copy(result.module.unwrap_single(), &path)
+                .expect("unable to copy the module to the rust-gpu cache");
+        }
+        Ok(path)
+    }
+}
+
+/// The default configuration with one setting changed at a time: no SPIR-T passes, each pass on
+/// its own, other Vulkan targets and the other panic strategies.
+pub fn matrix() -> Vec<RustGpuConfig> {
+    let base = RustGpuConfig {
+        dump_spirt_passes: false,
+        ..Default::default()
+    };
+    let mut configs = vec![RustGpuConfig {
+        name: "rust-gpu default".to_string(),
+        ..base.clone()
+    }];
+    let passes: [(&str, &'static [&'static str]); 3] = [
+        ("none", &[]),
+        ("reduce", &["reduce"]),
+        ("fuse_selects", &["fuse_selects"]),
+    ];
+    for (name, spirt_passes) in passes {
+        configs.push(RustGpuConfig {
+            name: format!("rust-gpu passes={name}"),
+            spirt_passes,
+            ..base.clone()
+        });
+    }
+    for target in ["spirv-unknown-vulkan1.0", "spirv-unknown-vulkan1.2"] {
+        configs.push(RustGpuConfig {
+            name: format!("rust-gpu {}", target.trim_start_matches("spirv-unknown-")),
+            target,
+            ..base.clone()
+        });
+    }
+    let panic_strategies = [
+        (
+            "debug-printf",
+            ShaderPanicStrategy::DebugPrintfThenExit {
+                print_inputs: false,
+                print_backtrace: false,
+            },
+        ),
+        (
+            "unreachable",
+            ShaderPanicStrategy::UNSOUND_DO_NOT_USE_UndefinedBehaviorViaUnreachable,
+        ),
+    ];
+    for (name, panic_strategy) in panic_strategies {
+        configs.push(RustGpuConfig {
+            name: format!("rust-gpu panic={name}"),
+            panic_strategy,
+            ..base.clone()
+        });
+    }
+    configs
+}
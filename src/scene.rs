@@ -0,0 +1,113 @@
+use compute_shader::{
+    glam::{uvec2, vec3, Vec3},
+    util::hash_noise,
+    Triangle,
+};
+use std::path::Path;
+
+/// Small random triangles scattered through the same -1..1 cube the random rays start in.
+pub fn random_triangles(count: u32) -> Vec<Triangle> {
+    (0..count)
+        .map(|i| {
+            let coord = uvec2(i, 0);
+            let tri = Triangle::random(coord, 0);
+            let center = vec3(
+                hash_noise(coord, 9),
+                hash_noise(coord, 10),
+                hash_noise(coord, 11),
+            ) * 2.0
+                - 1.0;
+            Triangle {
+                a: center + tri.a * 0.1,
+                b: center + tri.b * 0.1,
+                c: center + tri.c * 0.1,
+            }
+        })
+        .collect()
+}
+
+/// Loads the `v` and `f` lines of an OBJ file, see [`parse_obj`].
+pub fn load_obj(path: &Path) -> Result<Vec<Triangle>, String> {
+    let src = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    parse_obj(&src)
+}
+
+/// Reads the `v` and `f` lines of an OBJ file, polygons are fan triangulated. The mesh is scaled to
+/// fit the -1..1 cube so it lines up with the random rays. Errors name the offending line.
+pub fn parse_obj(src: &str) -> Result<Vec<Triangle>, String> {
+    let mut positions = Vec::new();
+    let mut triangles = Vec::new();
+    for (number, line) in src.lines().enumerate() {
+        let error = |message: String| format!("line {}: {message}", number + 1);
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("v") => {
+                let p = parts
+                    .take(3)
+                    .map(|s| s.parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| error(format!("invalid vertex ({e})")))?;
+                if p.len() < 3 {
+                    return Err(error("vertex with less than 3 coordinates".to_string()));
+                }
+                positions.push(vec3(p[0], p[1], p[2]));
+            }
+            Some("f") => {
+                let face = parts
+                    .map(|s| {
+                        // `v`, `v/vt`, `v//vn` or `v/vt/vn`, negative indices are relative to the
+                        // last vertex.
+                        let (i, _) = s.split_once('/').unwrap_or((s, ""));
+                        let i = i
+                            .parse::<i64>()
+                            .map_err(|e| error(format!("invalid face index {s:?} ({e})")))?;
+                        let count = positions.len() as i64;
+                        if i == 0 {
+                            Err(error("face index 0, OBJ indices start at 1".to_string()))
+                        } else if i.abs() > count {
+                            Err(error(format!(
+                                "face index {i} out of range of the {count} vertices so far"
+                            )))
+                        } else if i < 0 {
+                            Ok((count + i) as usize)
+                        } else {
+                            Ok((i - 1) as usize)
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if face.len() < 3 {
+                    return Err(error("face with less than 3 vertices".to_string()));
+                }
+                for i in 1..face.len() - 1 {
+                    triangles.push(Triangle {
+                        a: positions[face[0]],
+                        b: positions[face[i]],
+                        c: positions[face[i + 1]],
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+    if triangles.is_empty() {
+        return Err("no faces".to_string());
+    }
+
+    let (min, max) = triangles.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(-f32::MAX)),
+        |(min, max), t| {
+            (
+                min.min(t.a).min(t.b).min(t.c),
+                max.max(t.a).max(t.b).max(t.c),
+            )
+        },
+    );
+    let center = (min + max) * 0.5;
+    let scale = 2.0 / (max - min).max_element().max(f32::EPSILON);
+    for t in triangles.iter_mut() {
+        t.a = (t.a - center) * scale;
+        t.b = (t.b - center) * scale;
+        t.c = (t.c - center) * scale;
+    }
+    Ok(triangles)
+}
@@ -0,0 +1,105 @@
+//! Variants of `main` that get `settings` some other way than the uniform buffer at binding 1.
+//! Each language has its own entry point for them, `main_push_constant` and `main_constant`.
+//! `main_push_constant` is kept out of the other kernels, see [`with_push_constants`].
+
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Settings {
+    /// Read from push constants, see [`wgpu::Features::PUSH_CONSTANTS`].
+    PushConstant,
+    /// Only the size, known when the kernel is compiled. A specialization constant in rust-gpu,
+    /// `SIZE_CONSTANT` in the other languages. wgpu doesn't expose specialization constants yet,
+    /// so both are written in with [`specialize_spirv`] and [`specialize_source`].
+    Constant,
+}
+
+impl Settings {
+    pub const ALL: [Settings; 2] = [Settings::PushConstant, Settings::Constant];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Settings::PushConstant => "push constant",
+            Settings::Constant => "constant",
+        }
+    }
+
+    pub fn entry_point(&self) -> &'static str {
+        match self {
+            Settings::PushConstant => "main_push_constant",
+            Settings::Constant => "main_constant",
+        }
+    }
+}
+
+/// `main_push_constant` in WGSL, the GLSL and Slang ones are generated from it.
+pub const PUSH_CONSTANT_WGSL: &str = include_str!("compute_shader_push_constant.wgsl");
+
+/// The WGSL kernel `wgsl` with [`PUSH_CONSTANT_WGSL`] appended. naga rejects a module declaring
+/// push constants on adapters without [`wgpu::Features::PUSH_CONSTANTS`], whichever entry point
+/// runs, so only the settings variants are built from it. In rust-gpu it's the
+/// [`crate::rust_gpu::PUSH_CONSTANT_CRATE`] crate.
+pub fn with_push_constants(wgsl: &str) -> String {
+    format!("{wgsl}\n{PUSH_CONSTANT_WGSL}")
+}
+
+/// `SpecId` of the size in `main_constant` of the rust-gpu kernel.
+pub const SIZE_SPEC_ID: u32 = 0;
+
+/// The word range of each instruction after the header.
+fn instructions(words: &[u32]) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut offset = 5;
+    std::iter::from_fn(move || {
+        let count = (*words.get(offset)? >> 16) as usize;
+        let start = offset;
+        offset += count.max(1);
+        Some(start..start + count)
+    })
+}
+
+/// Sets the default of the specialization constant with `SpecId` `id` to `value`, the SPIR-V a
+/// specialized pipeline would see.
+pub fn specialize_spirv(words: &mut [u32], id: u32, value: u32) -> Result<(), String> {
+    const OP_SPEC_CONSTANT: u32 = 50;
+    const OP_DECORATE: u32 = 71;
+    const SPEC_ID: u32 = 1;
+
+    let target = instructions(words)
+        .map(|range| &words[range])
+        .find(|op| op.len() == 4 && op[0] & 0xffff == OP_DECORATE && op[2..] == [SPEC_ID, id])
+        .map(|op| op[1])
+        .ok_or_else(|| format!("no specialization constant with SpecId {id}"))?;
+    let value_index = instructions(words)
+        .find(|range| {
+            let op = &words[range.clone()];
+            op.len() == 4 && op[0] & 0xffff == OP_SPEC_CONSTANT && op[2] == target
+        })
+        .map(|range| range.start + 3)
+        .ok_or_else(|| format!("SpecId {id} isn't a 32 bit OpSpecConstant"))?;
+    words[value_index] = value;
+    Ok(())
+}
+
+/// Writes `size` into the `SIZE_CONSTANT` declaration of the WGSL or Slang kernel, whose value is
+/// always the last word of the line. The GLSL kernel is generated from the specialized WGSL.
+pub fn specialize_source(source: &str, size: u32) -> Result<String, String> {
+    const DECLARATIONS: [&str; 2] = ["const SIZE_CONSTANT:", "static const uint SIZE_CONSTANT "];
+    let mut found = false;
+    let mut out = String::new();
+    for line in source.lines() {
+        if DECLARATIONS.iter().any(|prefix| line.starts_with(prefix)) {
+            let semicolon = if line.ends_with(';') { ";" } else { "" };
+            let (declaration, _) = line.rsplit_once(' ').unwrap();
+            out.push_str(&format!("{declaration} {size}u{semicolon}"));
+            found = true;
+        } else {
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+    if found {
+        Ok(out)
+    } else {
+        Err("no SIZE_CONSTANT declaration".to_string())
+    }
+}
@@ -0,0 +1,249 @@
+use crate::hash::fnv1a;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+    str::FromStr,
+};
+
+/// How slangc gets to SPIR-V.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlangEmit {
+    Direct,
+    ViaGlsl,
+}
+
+impl FromStr for SlangEmit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "direct" => Ok(SlangEmit::Direct),
+            "glsl" => Ok(SlangEmit::ViaGlsl),
+            _ => Err(format!(
+                "unknown slang emit path {s:?}, expected direct or glsl"
+            )),
+        }
+    }
+}
+
+pub fn parse_opt_level(s: &str) -> Result<u8, String> {
+    match s.parse() {
+        Ok(level) if level <= 3 => Ok(level),
+        _ => Err(format!(
+            "slang optimization level must be 0 to 3, got {s:?}"
+        )),
+    }
+}
+
+/// Profiles tried by [`matrix`].
+pub const MATRIX_PROFILES: [&str; 3] = ["sm_5_0", "sm_6_5", "glsl_450"];
+
+/// Every optimization level and emit path for each of [`MATRIX_PROFILES`], on top of `base`.
+pub fn matrix(base: &SlangCompiler) -> Vec<SlangCompiler> {
+    let mut compilers = Vec::new();
+    for profile in MATRIX_PROFILES {
+        for emit in [SlangEmit::Direct, SlangEmit::ViaGlsl] {
+            for opt_level in 0..=3 {
+                compilers.push(SlangCompiler {
+                    profile: profile.to_string(),
+                    opt_level: Some(opt_level),
+                    emit: Some(emit),
+                    ..base.clone()
+                });
+            }
+        }
+    }
+    compilers
+}
+
+/// Names of the `[numthreads(..)]` functions in `source`.
+pub fn entry_points(source: &str) -> Vec<&str> {
+    let mut lines = source.lines();
+    let mut entry_points = Vec::new();
+    while let Some(line) = lines.next() {
+        if line.starts_with("[numthreads(") {
+            let signature = lines.next().expect("numthreads without a function");
+            let name = signature
+                .split_once(' ')
+                .and_then(|(_, rest)| rest.split_once('('))
+                .unwrap_or_else(|| panic!("expected an entry point: {signature}"))
+                .0;
+            entry_points.push(name);
+        }
+    }
+    entry_points
+}
+
+/// The module for `entry_point` in `dir`, like the ones build.rs compiles.
+pub fn module_path(dir: &Path, entry_point: &str) -> PathBuf {
+    dir.join(format!("{entry_point}.spv"))
+}
+
+/// Records which source `spv` was compiled from, next to it.
+pub fn stamp_path(spv: &Path) -> PathBuf {
+    spv.with_extension("spv.source-hash")
+}
+
+fn source_hash(source: &Path) -> String {
+    let source = std::fs::read(source).expect("unable to read slang source");
+    format!("{:016x}", fnv1a(&source))
+}
+
+pub fn write_stamp(source: &Path, spv: &Path) {
+    std::fs::write(stamp_path(spv), source_hash(source)).expect("unable to write stamp");
+}
+
+/// Whether `spv` was compiled from the current contents of `source`, false without a stamp.
+pub fn is_fresh(source: &Path, spv: &Path) -> bool {
+    std::fs::read_to_string(stamp_path(spv)).is_ok_and(|hash| hash.trim() == source_hash(source))
+}
+
+#[derive(Debug)]
+pub enum SlangError {
+    /// slangc couldn't be started, usually because it isn't on the `PATH`
+    Spawn(PathBuf, std::io::Error),
+    Failed {
+        entry_point: String,
+        status: ExitStatus,
+        stderr: String,
+    },
+    /// slangc exited successfully without writing the module
+    NoOutput(PathBuf),
+}
+
+impl fmt::Display for SlangError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SlangError::Spawn(binary, err) => write!(f, "unable to run {binary:?}: {err}"),
+            SlangError::Failed {
+                entry_point,
+                status,
+                stderr,
+            } => write!(f, "slangc failed on {entry_point} ({status}):\n{stderr}"),
+            SlangError::NoOutput(path) => write!(f, "slangc didn't write {path:?}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SlangCompiler {
+    pub binary: PathBuf,
+    pub profile: String,
+    /// slangc's own default when `None`
+    pub opt_level: Option<u8>,
+    /// slangc's own default when `None`
+    pub emit: Option<SlangEmit>,
+    pub extra_flags: Vec<String>,
+}
+
+impl Default for SlangCompiler {
+    fn default() -> Self {
+        Self {
+            binary: "slangc".into(),
+            profile: "sm_5_0".into(),
+            opt_level: None,
+            emit: None,
+            extra_flags: Vec::new(),
+        }
+    }
+}
+
+impl SlangCompiler {
+    pub fn args(&self, source: &Path, entry_point: &str, output: &Path) -> Vec<String> {
+        let mut args = vec![source.to_string_lossy().to_string()];
+        for arg in ["-target", "spirv", "-profile", &self.profile] {
+            args.push(arg.to_string());
+        }
+        if let Some(level) = self.opt_level {
+            args.push(format!("-O{level}"));
+        }
+        match self.emit {
+            Some(SlangEmit::Direct) => args.push("-emit-spirv-directly".to_string()),
+            Some(SlangEmit::ViaGlsl) => args.push("-emit-spirv-via-glsl".to_string()),
+            None => (),
+        }
+        args.extend(self.extra_flags.iter().cloned());
+        for arg in ["-stage", "compute", "-entry", entry_point, "-o"] {
+            args.push(arg.to_string());
+        }
+        args.push(output.to_string_lossy().to_string());
+        args
+    }
+
+    /// Short description of the settings, used to name benchmark variants.
+    pub fn name(&self) -> String {
+        let mut name = self.profile.clone();
+        if let Some(level) = self.opt_level {
+            name.push_str(&format!(" -O{level}"));
+        }
+        match self.emit {
+            Some(SlangEmit::Direct) => name.push_str(" direct"),
+            Some(SlangEmit::ViaGlsl) => name.push_str(" via glsl"),
+            None => (),
+        }
+        for flag in &self.extra_flags {
+            name.push(' ');
+            name.push_str(flag);
+        }
+        name
+    }
+
+    /// Where [`Self::compile_cached`] keeps the module, keyed by the source contents, the binary
+    /// and every flag. Clear `cache_dir` after updating slangc.
+    pub fn cache_path(&self, source: &Path, entry_point: &str, cache_dir: &Path) -> PathBuf {
+        let mut key = std::fs::read(source).expect("unable to read slang source");
+        key.extend(self.binary.to_string_lossy().bytes());
+        for arg in self.args(Path::new(""), entry_point, Path::new("")) {
+            key.push(0);
+            key.extend(arg.bytes());
+        }
+        cache_dir.join(format!("{entry_point}-{:016x}.spv", fnv1a(&key)))
+    }
+
+    /// Compiles into `cache_dir` unless a module for the same source and settings is already
+    /// there, returns the path of the module.
+    pub fn compile_cached(
+        &self,
+        source: &Path,
+        entry_point: &str,
+        cache_dir: &Path,
+    ) -> Result<PathBuf, SlangError> {
+        let path = self.cache_path(source, entry_point, cache_dir);
+        if !path.exists() {
+            std::fs::create_dir_all(cache_dir).expect("unable to create the slang cache");
+            self.compile(source, entry_point, &path)?;
+        }
+        Ok(path)
+    }
+
+    /// Compiles one entry point of `source` to `output`. The module is written next to `output`
+    /// first and only moved over it on success, so a failed compile leaves the previous module
+    /// untouched rather than a partial one.
+    pub fn compile(
+        &self,
+        source: &Path,
+        entry_point: &str,
+        output: &Path,
+    ) -> Result<(), SlangError> {
+        let partial = output.with_extension("partial.spv");
+        let _ = std::fs::remove_file(&partial);
+        let out = Command::new(&self.binary)
+            .args(self.args(source, entry_point, &partial))
+            .output()
+            .map_err(|err| SlangError::Spawn(self.binary.clone(), err))?;
+        let stderr = String::from_utf8_lossy(&out.stderr).trim().to_string();
+        if !out.status.success() {
+            let _ = std::fs::remove_file(&partial);
+            return Err(SlangError::Failed {
+                entry_point: entry_point.to_string(),
+                status: out.status,
+                stderr,
+            });
+        }
+        if !stderr.is_empty() {
+            println!("slangc {entry_point}: {stderr}");
+        }
+        std::fs::rename(&partial, output).map_err(|_| SlangError::NoOutput(output.to_path_buf()))
+    }
+}
@@ -0,0 +1,221 @@
+use num_traits::FromPrimitive;
+use std::{collections::BTreeMap, fmt::Write, path::Path, process::Command};
+use wgpu::ShaderSource;
+
+/// SPIR-V of a shader module, WGSL and GLSL are translated by naga's SPIR-V backend like wgpu
+/// does on Vulkan.
+pub fn spirv_words(source: &ShaderSource) -> Vec<u32> {
+    match source {
+        ShaderSource::SpirV(words) => words.to_vec(),
+        _ => match crate::roundtrip::translate(source) {
+            Ok(ShaderSource::SpirV(words)) => words.into_owned(),
+            Ok(_) => unreachable!(),
+            Err(err) => panic!("{err}"),
+        },
+    }
+}
+
+/// (opcode, operands) of each instruction after the header.
+fn instructions(words: &[u32]) -> impl Iterator<Item = (u32, &[u32])> {
+    let mut rest = words.get(5..).unwrap_or_default();
+    std::iter::from_fn(move || {
+        let first = *rest.first()?;
+        let len = (first >> 16) as usize;
+        if len == 0 || len > rest.len() {
+            return None;
+        }
+        let (instruction, tail) = rest.split_at(len);
+        rest = tail;
+        Some((first & 0xffff, &instruction[1..]))
+    })
+}
+
+fn op_name(opcode: u32) -> String {
+    match spirv::Op::from_u32(opcode) {
+        Some(op) => format!("Op{op:?}"),
+        None => format!("Op{opcode}"),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SpirvStats {
+    pub bytes: usize,
+    pub instructions: usize,
+    pub functions: usize,
+    /// `OpLoopMerge`s
+    pub loops: usize,
+    /// `OpBranchConditional`s and `OpSwitch`es
+    pub branches: usize,
+    pub calls: usize,
+    /// Count of each opcode by name
+    pub ops: BTreeMap<String, usize>,
+}
+
+impl SpirvStats {
+    pub fn new(words: &[u32]) -> Self {
+        let mut stats = SpirvStats {
+            bytes: words.len() * 4,
+            ..Default::default()
+        };
+        for (opcode, _) in instructions(words) {
+            stats.add(opcode);
+        }
+        stats
+    }
+
+    /// Only counts the functions `entry_point` calls, directly or not, so entry points sharing a
+    /// module can be compared. `bytes` is the size of those functions. `None` if there's no such
+    /// entry point.
+    pub fn for_entry_point(words: &[u32], entry_point: &str) -> Option<Self> {
+        use spirv::Op;
+        let mut entry = None;
+        // Instructions of each function by id
+        let mut functions = BTreeMap::<u32, Vec<(u32, &[u32])>>::new();
+        let mut current = None;
+        for (opcode, operands) in instructions(words) {
+            match Op::from_u32(opcode) {
+                Some(Op::EntryPoint) if decode_string(&operands[2..]).0 == entry_point => {
+                    entry = Some(operands[1]);
+                }
+                Some(Op::Function) => current = Some(operands[1]),
+                _ => (),
+            }
+            if let Some(id) = current {
+                functions.entry(id).or_default().push((opcode, operands));
+            }
+            if opcode == Op::FunctionEnd as u32 {
+                current = None;
+            }
+        }
+
+        let mut stats = SpirvStats::default();
+        let mut reached = vec![entry?];
+        let mut i = 0;
+        while let Some(id) = reached.get(i) {
+            for (opcode, operands) in functions.get(id).into_iter().flatten() {
+                stats.add(*opcode);
+                stats.bytes += (operands.len() + 1) * 4;
+                if *opcode == Op::FunctionCall as u32 && !reached.contains(&operands[2]) {
+                    reached.push(operands[2]);
+                }
+            }
+            i += 1;
+        }
+        Some(stats)
+    }
+
+    fn add(&mut self, opcode: u32) {
+        self.instructions += 1;
+        match spirv::Op::from_u32(opcode) {
+            Some(spirv::Op::Function) => self.functions += 1,
+            Some(spirv::Op::LoopMerge) => self.loops += 1,
+            Some(spirv::Op::BranchConditional | spirv::Op::Switch) => self.branches += 1,
+            Some(spirv::Op::FunctionCall) => self.calls += 1,
+            _ => (),
+        }
+        *self.ops.entry(op_name(opcode)).or_default() += 1;
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} bytes, {} instructions, {} functions, {} loops, {} branches, {} calls",
+            self.bytes, self.instructions, self.functions, self.loops, self.branches, self.calls
+        )
+    }
+
+    /// Opcodes by count, most used first.
+    pub fn histogram(&self) -> String {
+        let mut ops: Vec<_> = self.ops.iter().collect();
+        ops.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let mut out = String::new();
+        for (name, count) in ops {
+            writeln!(out, "{count:>6} {name}").unwrap();
+        }
+        out
+    }
+}
+
+/// Index of the literal string operand of `opcode`, if it has one.
+fn string_operand(opcode: u32) -> Option<usize> {
+    use spirv::Op;
+    match Op::from_u32(opcode)? {
+        Op::Extension | Op::SourceExtension => Some(0),
+        Op::Name | Op::ExtInstImport | Op::String => Some(1),
+        Op::MemberName | Op::EntryPoint => Some(2),
+        _ => None,
+    }
+}
+
+fn decode_string(words: &[u32]) -> (String, usize) {
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    let string = String::from_utf8_lossy(&bytes[..len]).to_string();
+    (string, len / 4 + 1)
+}
+
+/// One instruction per line with its operands as raw words, strings decoded. `spirv-dis` gives a
+/// nicer listing when it's installed, see [`dump`].
+pub fn disassemble(words: &[u32]) -> String {
+    let mut out = String::new();
+    if let [magic, version, generator, bound, ..] = words {
+        writeln!(
+            out,
+            "; magic {magic:#010x}, version {}.{}, generator {generator:#010x}, bound {bound}",
+            (version >> 16) & 0xff,
+            (version >> 8) & 0xff
+        )
+        .unwrap();
+    }
+    for (opcode, operands) in instructions(words) {
+        out.push_str(&op_name(opcode));
+        let mut i = 0;
+        while i < operands.len() {
+            if Some(i) == string_operand(opcode) {
+                let (string, len) = decode_string(&operands[i..]);
+                write!(out, " {string:?}").unwrap();
+                i += len;
+            } else {
+                write!(out, " {}", operands[i]).unwrap();
+                i += 1;
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Writes `<name>.spv`, `<name>.spvasm` and `<name>.stats.txt` to `dir`.
+pub fn dump(dir: &Path, name: &str, source: &ShaderSource) -> SpirvStats {
+    let file_name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let words = spirv_words(source);
+    let stats = SpirvStats::new(&words);
+
+    let spv_path = dir.join(format!("{file_name}.spv"));
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    std::fs::write(&spv_path, bytes).expect("unable to write spir-v");
+
+    let disassembly = Command::new("spirv-dis")
+        .arg(&spv_path)
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map(|out| String::from_utf8_lossy(&out.stdout).to_string())
+        .unwrap_or_else(|| disassemble(&words));
+    std::fs::write(dir.join(format!("{file_name}.spvasm")), disassembly)
+        .expect("unable to write disassembly");
+    std::fs::write(
+        dir.join(format!("{file_name}.stats.txt")),
+        format!("{}\n\n{}", stats.summary(), stats.histogram()),
+    )
+    .expect("unable to write stats");
+    stats
+}
@@ -0,0 +1,102 @@
+use one_thread_gpu_bench::codegen;
+
+#[test]
+fn generated_shaders_are_fresh() {
+    for (path, expected) in codegen::outputs() {
+        let on_disk = std::fs::read_to_string(&path).expect("unable to read shader");
+        assert!(
+            on_disk == expected,
+            "{} is out of date, run with `--generate-shaders`",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn wgsl_is_valid() {
+    let module = naga::front::wgsl::parse_str(include_str!("../src/compute_shader.wgsl"))
+        .expect("invalid wgsl");
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .expect("wgsl doesn't validate");
+}
+
+const KERNEL: &str = "\
+@group(0) @binding(0) var<storage, read_write> data: array<f32>;
+@group(0) @binding(1) var<uniform> settings: vec4<u32>;
+@group(0) @binding(2) var<storage, read> inputs: array<f32>;
+var<push_constant> push_settings: vec4<u32>;
+
+@compute @workgroup_size(1, 1, 1)
+fn main_a() {
+    data[0] = inputs[settings.x];
+}
+
+@compute @workgroup_size(1, 1, 1)
+fn main_b() {
+    data[0] = f32(push_settings.y);
+}
+";
+
+#[test]
+fn slang_gets_vulkan_bindings() {
+    let slang = codegen::slang(KERNEL).unwrap();
+    let globals: Vec<_> = slang
+        .lines()
+        .filter(|line| line.starts_with("[[vk::"))
+        .collect();
+    assert_eq!(
+        globals,
+        [
+            "[[vk::binding(0, 0)]] RWByteAddressBuffer data;",
+            "[[vk::binding(1, 0)]] cbuffer settings_block { uint4 settings; }",
+            "[[vk::binding(2, 0)]] ByteAddressBuffer inputs;",
+            "[[vk::push_constant]] cbuffer push_settings_block { uint4 push_settings; }",
+        ]
+    );
+    assert!(!slang.contains("register("));
+    assert_eq!(
+        one_thread_gpu_bench::slang::entry_points(&slang),
+        ["main_a", "main_b"]
+    );
+}
+
+#[test]
+fn glsl_has_one_entry_point_per_module() {
+    let spaces = [
+        (
+            "main_a",
+            naga::AddressSpace::Storage {
+                access: naga::StorageAccess::LOAD,
+            },
+        ),
+        ("main_b", naga::AddressSpace::PushConstant),
+    ];
+    for (entry_point, space) in spaces {
+        let glsl = codegen::glsl(KERNEL, entry_point).unwrap();
+        let module = naga::front::glsl::Frontend::default()
+            .parse(&naga::ShaderStage::Compute.into(), &glsl)
+            .unwrap_or_else(|errors| panic!("{errors:?}\n{glsl}"));
+        assert_eq!(module.entry_points.len(), 1);
+        assert_eq!(module.entry_points[0].name, "main");
+        assert!(
+            module
+                .global_variables
+                .iter()
+                .any(|(_, g)| g.space == space),
+            "{glsl}"
+        );
+    }
+    assert!(codegen::glsl(KERNEL, "main_c").is_err());
+    let other_group = KERNEL.replace("@group(0) @binding(2)", "@group(1) @binding(2)");
+    assert!(codegen::glsl(&other_group, "main_a").is_err());
+}
+
+#[test]
+fn errors_name_the_line() {
+    let err = codegen::slang("fn f() -> u32 {\n    return 1u +;\n}\n").unwrap_err();
+    assert!(err.contains(":2:"), "{err}");
+}
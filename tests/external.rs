@@ -0,0 +1,49 @@
+use one_thread_gpu_bench::external;
+use std::path::PathBuf;
+use wgpu::ShaderSource;
+
+fn write(name: &str, contents: &[u8]) -> PathBuf {
+    let dir = std::env::temp_dir().join("one_thread_gpu_bench_external");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn sources_follow_the_extension() {
+    let wgsl = write("kernel.wgsl", b"@compute @workgroup_size(1) fn run() {}");
+    let (module, entry_point) = external::load(&wgsl, "run").unwrap();
+    assert!(matches!(module.source, ShaderSource::Wgsl(_)));
+    assert_eq!(entry_point, "run");
+
+    let words: [u32; 5] = [0x07230203, 0x00010000, 0, 1, 0];
+    let spv = write("kernel.spv", bytemuck::cast_slice(&words));
+    let (module, entry_point) = external::load(&spv, "main").unwrap();
+    match module.source {
+        ShaderSource::SpirV(loaded) => assert_eq!(&*loaded, &words),
+        _ => panic!("expected spir-v"),
+    }
+    assert_eq!(entry_point, "main");
+
+    let glsl = write("kernel.comp", b"#version 450\nvoid main() {}\n");
+    let (module, entry_point) = external::load(&glsl, "main_bvh").unwrap();
+    match module.source {
+        ShaderSource::Glsl { defines, .. } => assert!(defines.contains_key("MAIN_BVH")),
+        _ => panic!("expected glsl"),
+    }
+    // GLSL entry points are always `main`
+    assert_eq!(entry_point, "main");
+}
+
+#[test]
+fn bad_files_are_reported() {
+    let missing = std::env::temp_dir().join("one_thread_gpu_bench_missing.wgsl");
+    assert!(external::load(&missing, "main").is_err_and(|err| err.contains("unable to read")));
+    let hlsl = write("kernel.hlsl", b"");
+    assert!(external::load(&hlsl, "main").is_err());
+    let truncated = write("truncated.spv", &[3, 2, 0x23, 7, 0]);
+    assert!(external::load(&truncated, "main").is_err());
+    let not_spirv = write("not_spirv.spv", &[0; 8]);
+    assert!(external::load(&not_spirv, "main").is_err_and(|err| err.contains("magic number")));
+}
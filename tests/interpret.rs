@@ -0,0 +1,47 @@
+use one_thread_gpu_bench::slang_spv_path;
+use std::process::Command;
+
+fn interpret(size: u32) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_one_thread_gpu_bench"))
+        .args(["--interpret", "--size", &size.to_string()])
+        .output()
+        .expect("failed to run the benchmark");
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    assert!(
+        output.status.success() || stdout.contains("differs from the CPU"),
+        "{stdout}\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    stdout
+}
+
+#[test]
+fn wgsl_matches_cpu() {
+    for size in [1, 7, 16] {
+        let stdout = interpret(size);
+        assert!(
+            stdout.contains("wgsl: matches the CPU"),
+            "size {size}\n{stdout}"
+        );
+    }
+}
+
+#[test]
+fn slang_matches_cpu() {
+    let path = slang_spv_path("main");
+    if !path.exists() {
+        println!("skipped, {path:?} not found, slangc wasn't found when building");
+        return;
+    }
+    for size in [1, 4] {
+        let stdout = interpret(size);
+        assert!(
+            stdout.contains("slang: matches the CPU"),
+            "size {size}\n{stdout}"
+        );
+        assert!(
+            !stdout.contains("cpu != gpu") && !stdout.contains("differs from the CPU"),
+            "size {size}\n{stdout}"
+        );
+    }
+}
@@ -0,0 +1,12 @@
+use one_thread_gpu_bench::Options;
+use structopt::StructOpt;
+
+fn parse(args: &[&str]) -> Result<Options, structopt::clap::Error> {
+    Options::from_iter_safe(std::iter::once("one_thread_gpu_bench").chain(args.iter().copied()))
+}
+
+#[test]
+fn sphere_rays_need_an_rng() {
+    assert!(parse(&["--sphere-rays"]).is_err());
+    assert!(parse(&["--rng", "pcg32", "--sphere-rays"]).is_ok());
+}
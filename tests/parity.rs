@@ -0,0 +1,544 @@
+//! The Rust and WGSL kernels are maintained by hand, the Slang and GLSL ones are generated from
+//! `compute_shader.wgsl` by naga. These tests run the WGSL, Slang and GLSL kernels, and their naga
+//! translations, in the CPU interpreter and compare them against the Rust kernels, which run
+//! natively since they are the same code rust-gpu compiles.
+//!
+//! Everything integer (hashes, generator bits, hit counts) has to match exactly. Floats have to be
+//! within [`TOLERANCE`].
+
+use compute_shader::{
+    bvh::{Bvh, BVH_STACK_SIZE},
+    edge_cases::{self, EDGE_CASES, HIT_LEN},
+    micro,
+    rng::{self, RNG_NAMES},
+    util, Stats,
+};
+use naga::{
+    valid::{Capabilities, ModuleInfo, ValidationFlags, Validator},
+    ArraySize, Constant, ConstantInner, Handle, Module, ScalarValue, Statement, TypeInner,
+};
+use one_thread_gpu_bench::{codegen, interpreter, scene, slang_spv_path};
+
+/// Relative difference allowed between floats, with magnitudes below 1 treated as 1. The
+/// interpreter evaluates everything in the same order as the Rust code with the same `f32`
+/// functions so the results are normally bit exact, this leaves room for a translation that
+/// reassociates or fuses operations.
+const TOLERANCE: f32 = 1e-5;
+
+const WGSL: &str = include_str!("../src/compute_shader.wgsl");
+const RUST_LIB: &str = include_str!("../shaders/compute_shader/src/lib.rs");
+const RUST_UTIL: &str = include_str!("../shaders/compute_shader/src/util.rs");
+
+fn validate(module: &Module) -> ModuleInfo {
+    Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(module)
+        .expect("invalid module")
+}
+
+fn spv_to_module(spv: &[u8]) -> Module {
+    naga::front::spv::parse_u8_slice(spv, &Default::default()).expect("invalid spv")
+}
+
+fn wgsl_to_module(wgsl: &str) -> Module {
+    naga::front::wgsl::parse_str(wgsl).unwrap_or_else(|e| panic!("{}", e.emit_to_string(wgsl)))
+}
+
+fn module_to_spv(module: &Module) -> Vec<u8> {
+    let words = naga::back::spv::write_vec(module, &validate(module), &Default::default(), None)
+        .expect("unable to write spv");
+    bytemuck::cast_slice(&words).to_vec()
+}
+
+fn module_to_wgsl(module: &Module) -> String {
+    naga::back::wgsl::write_string(
+        module,
+        &validate(module),
+        naga::back::wgsl::WriterFlags::empty(),
+    )
+    .expect("unable to write wgsl")
+}
+
+/// The WGSL kernel as written and after a round trip through SPIR-V.
+fn wgsl_variants() -> Vec<(&'static str, Module)> {
+    let wgsl = wgsl_to_module(WGSL);
+    let spv = spv_to_module(&module_to_spv(&wgsl));
+    vec![("wgsl", wgsl), ("wgsl -> spv", spv)]
+}
+
+/// The GLSL kernel as generated and after a round trip through SPIR-V. GLSL only has one entry
+/// point per module, so `entry_point` is generated on its own and given its name back.
+fn glsl_variants(entry_point: &str) -> Vec<(&'static str, Module)> {
+    let source = codegen::glsl(WGSL, entry_point).unwrap();
+    let mut glsl = naga::front::glsl::Frontend::default()
+        .parse(&naga::ShaderStage::Compute.into(), &source)
+        .unwrap_or_else(|errors| panic!("invalid glsl: {errors:?}"));
+    glsl.entry_points[0].name = entry_point.to_string();
+    let mut spv = glsl.clone();
+    for (_, function) in spv.functions.iter_mut() {
+        end_cases(&mut function.body);
+    }
+    let spv = spv_to_module(&module_to_spv(&spv));
+    vec![("glsl", glsl), ("glsl -> spv", spv)]
+}
+
+/// naga's GLSL backend writes cases as `case 1u: { ...; break; }`. The SPIR-V it writes for the
+/// `break` in a nested block leaves an unreachable block behind, which its SPIR-V frontend reads
+/// back as a `break` after the switch, and the interpreter would take that for a return. This
+/// drops the `break` and ends the case like the WGSL frontend does.
+fn end_cases(block: &mut naga::Block) {
+    for statement in block.iter_mut() {
+        match statement {
+            Statement::Block(block) => end_cases(block),
+            Statement::If { accept, reject, .. } => {
+                end_cases(accept);
+                end_cases(reject);
+            }
+            Statement::Loop {
+                body, continuing, ..
+            } => {
+                end_cases(body);
+                end_cases(continuing);
+            }
+            Statement::Switch { cases, .. } => {
+                for case in cases {
+                    end_cases(&mut case.body);
+                    if let [.., Statement::Block(inner)] = &mut case.body[..] {
+                        if let [.., Statement::Break] = &inner[..] {
+                            inner.cull(inner.len() - 1..);
+                            case.fall_through = false;
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// The WGSL and GLSL variants of `entry_point`.
+fn generated_variants(entry_point: &str) -> Vec<(&'static str, Module)> {
+    let mut variants = wgsl_variants();
+    variants.extend(glsl_variants(entry_point));
+    variants
+}
+
+/// The Slang kernel build.rs compiled, as is and after a round trip through WGSL, `None` when
+/// slangc wasn't found when building. slangc names every entry point `main`, so there's one module
+/// per entry point.
+fn slang_variants(entry_point: &str) -> Option<Vec<(&'static str, Module)>> {
+    let path = slang_spv_path(entry_point);
+    let Ok(spv) = std::fs::read(&path) else {
+        println!("skipped, {path:?} not found, slangc wasn't found when building");
+        return None;
+    };
+    let slang = spv_to_module(&spv);
+    let wgsl = wgsl_to_module(&module_to_wgsl(&slang));
+    Some(vec![("slang", slang), ("slang -> wgsl", wgsl)])
+}
+
+/// Runs `entry_point` with `settings` and `inputs` bound from binding 2 on, returns the output
+/// buffer.
+fn run(
+    module: &Module,
+    entry_point: &str,
+    settings: [u32; 4],
+    inputs: &[&[u8]],
+    output_len: usize,
+) -> Vec<u32> {
+    let mut buffers = vec![
+        vec![0; output_len * 4],
+        bytemuck::cast_slice(&settings).to_vec(),
+    ];
+    buffers.extend(inputs.iter().map(|input| input.to_vec()));
+    interpreter::run(module, entry_point, &mut buffers);
+    bytemuck::cast_slice(&buffers[0]).to_vec()
+}
+
+fn close(a: f32, b: f32) -> bool {
+    a == b || (a - b).abs() <= TOLERANCE * a.abs().max(b.abs()).max(1.0)
+}
+
+fn assert_stats_close(name: &str, cpu: Stats, output: &[u32]) {
+    let floats = output
+        .iter()
+        .map(|u| f32::from_bits(*u))
+        .collect::<Vec<_>>();
+    let gpu = Stats::read(&floats, 0);
+    assert!(
+        close(cpu.sum, gpu.sum) && close(cpu.nearest_t, gpu.nearest_t),
+        "{name}\ncpu: {cpu:?}\ngpu: {gpu:?}"
+    );
+    assert_eq!(cpu.hit_count, gpu.hit_count, "{name}");
+}
+
+fn check_main(variants: Vec<(&str, Module)>, entry_point: &str) {
+    for size in [1, 5, 8] {
+        let cpu = compute_shader::compute(size);
+        for (name, module) in &variants {
+            let output = run(module, entry_point, [size; 4], &[], Stats::LEN);
+            assert_stats_close(&format!("{name} size {size}"), cpu, &output);
+        }
+    }
+}
+
+#[test]
+fn wgsl_main_matches_cpu() {
+    check_main(wgsl_variants(), "main");
+}
+
+#[test]
+fn glsl_main_matches_cpu() {
+    check_main(glsl_variants("main"), "main");
+}
+
+#[test]
+fn slang_main_matches_cpu() {
+    if let Some(variants) = slang_variants("main") {
+        check_main(variants, "main");
+    }
+}
+
+#[test]
+fn while_matches_cpu() {
+    check_main(generated_variants("main_while"), "main_while");
+}
+
+#[test]
+fn kahan_matches_cpu() {
+    for size in [1, 5, 8] {
+        let cpu = compute_shader::compute_kahan(size);
+        for (name, module) in generated_variants("main_kahan") {
+            let output = run(&module, "main_kahan", [size; 4], &[], Stats::LEN);
+            assert_stats_close(&format!("{name} size {size}"), cpu, &output);
+        }
+    }
+}
+
+#[test]
+fn rng_matches_cpu() {
+    let size = 4;
+    for (name, module) in generated_variants("main_rng") {
+        for (kind, rng_name) in RNG_NAMES.iter().enumerate() {
+            for sphere_rays in [false, true] {
+                let cpu = compute_shader::compute_rng(size, kind as u32, sphere_rays);
+                let settings = [size, kind as u32, sphere_rays as u32, 0];
+                let output = run(&module, "main_rng", settings, &[], Stats::LEN);
+                let name = format!("{name} {rng_name} sphere_rays {sphere_rays}");
+                assert_stats_close(&name, cpu, &output);
+            }
+        }
+    }
+}
+
+#[test]
+fn rng_dump_is_bit_exact() {
+    const COUNT: u32 = 16;
+    let mut cpu = vec![0; RNG_NAMES.len() * (COUNT * rng::DUMP_DIMENSIONS) as usize];
+    rng::dump(COUNT, &mut cpu);
+    for (name, module) in generated_variants("main_rng_dump") {
+        let output = run(&module, "main_rng_dump", [COUNT, 0, 0, 0], &[], cpu.len());
+        assert_eq!(output, cpu, "{name}");
+    }
+}
+
+#[test]
+fn hash_dump_is_bit_exact() {
+    const COUNT: u32 = 64;
+    let mut cpu = vec![0; util::HASH_NAMES.len() * COUNT as usize];
+    util::hash_dump(COUNT, &mut cpu);
+    for (name, module) in generated_variants("main_hash_dump") {
+        let output = run(&module, "main_hash_dump", [COUNT, 0, 0, 0], &[], cpu.len());
+        assert_eq!(output, cpu, "{name}");
+    }
+}
+
+#[test]
+fn micro_matches_cpu() {
+    const COUNT: u32 = 64;
+    for (kind, function) in micro::MICRO_NAMES.iter().enumerate() {
+        let kind = kind as u32;
+        let inputs: Vec<f32> = (0..COUNT).map(|i| micro::input(kind, i, COUNT)).collect();
+        let mut cpu = vec![0.0; COUNT as usize];
+        micro::run(kind, COUNT, &inputs, &mut cpu);
+        for (name, module) in generated_variants("main_micro") {
+            let input_bytes = [bytemuck::cast_slice(&inputs)];
+            let output = run(
+                &module,
+                "main_micro",
+                [COUNT, kind, 0, 0],
+                &input_bytes,
+                cpu.len(),
+            );
+            for (x, (gpu, cpu)) in inputs.iter().zip(output.iter().zip(&cpu)) {
+                let gpu = f32::from_bits(*gpu);
+                assert!(close(gpu, *cpu), "{name} {function}({x}): {gpu} != {cpu}");
+            }
+        }
+    }
+}
+
+/// The BVH scene in the layout the kernels bind it in.
+fn bvh_inputs() -> (Bvh, Vec<u8>, Vec<u8>) {
+    let bvh = Bvh::build(&scene::random_triangles(64));
+    let triangles = bvh
+        .triangles
+        .iter()
+        .map(|t| t.to_array())
+        .collect::<Vec<_>>();
+    let nodes = bvh
+        .nodes
+        .iter()
+        .map(|n| [n.min.to_array(), n.max.to_array()])
+        .collect::<Vec<_>>();
+    (
+        bvh,
+        bytemuck::cast_slice(&triangles).to_vec(),
+        bytemuck::cast_slice(&nodes).to_vec(),
+    )
+}
+
+/// Unlike the other kernels these have to match bit for bit, NaN and infinities included, the
+/// interpreter doesn't flush denormals or reassociate.
+#[test]
+fn edge_cases_match_cpu() {
+    let packed: Vec<_> = EDGE_CASES.iter().flat_map(|case| case.pack()).collect();
+    let mut cpu = vec![0.0; EDGE_CASES.len() * HIT_LEN];
+    edge_cases::intersect_cases(EDGE_CASES.len() as u32, &packed, &mut cpu);
+    let cases: Vec<_> = packed.iter().map(|v| v.to_array()).collect();
+
+    let count = EDGE_CASES.len() as u32;
+    for (name, module) in generated_variants("main_intersect") {
+        let inputs = [bytemuck::cast_slice(&cases)];
+        let output = run(
+            &module,
+            "main_intersect",
+            [count, 0, 0, 0],
+            &inputs,
+            cpu.len(),
+        );
+        let hits = output.chunks(HIT_LEN).zip(cpu.chunks(HIT_LEN));
+        for (case, (gpu, cpu)) in EDGE_CASES.iter().zip(hits) {
+            let cpu: Vec<_> = cpu.iter().map(|f| f.to_bits()).collect();
+            assert_eq!(gpu, cpu, "{name} {}", case.name);
+        }
+    }
+}
+
+#[test]
+fn bvh_matches_cpu() {
+    let size = 4;
+    let (bvh, triangles, nodes) = bvh_inputs();
+    let cpu = compute_shader::compute_bvh(size, &bvh.nodes, &bvh.triangles);
+    for (name, module) in generated_variants("main_bvh") {
+        let output = run(
+            &module,
+            "main_bvh",
+            [size; 4],
+            &[&triangles, &nodes],
+            Stats::LEN,
+        );
+        assert_stats_close(name, cpu, &output);
+    }
+}
+
+#[test]
+fn camera_matches_cpu() {
+    let size = 8;
+    let pixels = (size * size) as usize;
+    let (bvh, triangles, nodes) = bvh_inputs();
+    let mut image = vec![0.0; pixels];
+    let cpu = compute_shader::compute_camera(size, &bvh.nodes, &bvh.triangles, &mut image, 0);
+    for (name, module) in generated_variants("main_camera") {
+        let output = run(
+            &module,
+            "main_camera",
+            [size; 4],
+            &[&triangles, &nodes],
+            Stats::LEN + pixels,
+        );
+        assert_stats_close(name, cpu, &output);
+        for (i, (cpu, gpu)) in image.iter().zip(&output[Stats::LEN..]).enumerate() {
+            let gpu = f32::from_bits(*gpu);
+            assert!(close(*cpu, gpu), "{name} pixel {i}: {cpu} != {gpu}");
+        }
+    }
+}
+
+fn scalar(module: &Module, constant: Handle<Constant>) -> f64 {
+    match module.constants[constant].inner {
+        ConstantInner::Scalar { value, .. } => match value {
+            ScalarValue::Sint(i) => i as f64,
+            ScalarValue::Uint(u) => u as f64,
+            ScalarValue::Float(f) => f,
+            ScalarValue::Bool(b) => b as u32 as f64,
+        },
+        _ => panic!("{constant:?} isn't a scalar"),
+    }
+}
+
+fn wgsl_constant(module: &Module, name: &str) -> f64 {
+    let (constant, _) = module
+        .constants
+        .iter()
+        .find(|(_, c)| c.name.as_deref() == Some(name))
+        .unwrap_or_else(|| panic!("{name} not found"));
+    scalar(module, constant)
+}
+
+#[test]
+fn shader_constants_match() {
+    let wgsl = wgsl_to_module(WGSL);
+    let expected = [
+        ("F32_MAX", f32::MAX as f64),
+        ("TAU", std::f32::consts::TAU as f64),
+        ("RNG_COUNT", RNG_NAMES.len() as f64),
+        ("DUMP_DIMENSIONS", rng::DUMP_DIMENSIONS as f64),
+    ];
+    for (name, value) in expected {
+        assert_eq!(
+            wgsl_constant(&wgsl, name) as f32,
+            value as f32,
+            "wgsl {name}"
+        );
+    }
+
+    let kind_names = [
+        "RNG_HASH",
+        "RNG_PCG32",
+        "RNG_XORSHIFT",
+        "RNG_SOBOL",
+        "RNG_R2",
+    ];
+    let rust_kinds = [
+        rng::RNG_HASH,
+        rng::RNG_PCG32,
+        rng::RNG_XORSHIFT,
+        rng::RNG_SOBOL,
+        rng::RNG_R2,
+    ];
+    for (name, kind) in kind_names.iter().zip(rust_kinds) {
+        assert_eq!(wgsl_constant(&wgsl, name), kind as f64, "wgsl {name}");
+    }
+    assert_eq!(
+        wgsl_constant(&wgsl, "BVH_STACK_SIZE"),
+        BVH_STACK_SIZE as f64
+    );
+
+    // WGSL can't size arrays with a constant, so look at the traversal stack itself.
+    let closest_hit = wgsl
+        .functions
+        .iter()
+        .find(|(_, f)| f.name.as_deref() == Some("closest_hit"))
+        .unwrap()
+        .1;
+    let stack = closest_hit
+        .local_variables
+        .iter()
+        .find(|(_, v)| v.name.as_deref() == Some("stack"))
+        .unwrap()
+        .1;
+    match wgsl.types[stack.ty].inner {
+        TypeInner::Array {
+            size: ArraySize::Constant(c),
+            ..
+        } => assert_eq!(scalar(&wgsl, c), BVH_STACK_SIZE as f64, "wgsl stack size"),
+        ref inner => panic!("unexpected stack type {inner:?}"),
+    }
+}
+
+/// Numeric literals in `src`, skipping `//` comments, digits inside identifiers and tuple fields,
+/// with type suffixes like `u`, `u32` and `f` dropped.
+fn literals(src: &str) -> Vec<f64> {
+    let mut out = Vec::new();
+    for line in src.lines() {
+        let code = line.split("//").next().unwrap().as_bytes();
+        let mut i = 0;
+        while i < code.len() {
+            let prev = if i > 0 { code[i - 1] } else { b' ' };
+            if !code[i].is_ascii_digit() || prev.is_ascii_alphanumeric() || b"_.".contains(&prev) {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            let hex = code[i..].starts_with(b"0x");
+            if hex {
+                i += 2;
+                while i < code.len() && code[i].is_ascii_hexdigit() {
+                    i += 1;
+                }
+            } else {
+                while i < code.len()
+                    && (code[i].is_ascii_digit()
+                        || b".eE".contains(&code[i])
+                        || (b"+-".contains(&code[i]) && b"eE".contains(&code[i - 1])))
+                {
+                    i += 1;
+                }
+            }
+            let text = std::str::from_utf8(&code[start..i]).unwrap();
+            out.push(if hex {
+                u64::from_str_radix(&text[2..], 16).unwrap() as f64
+            } else {
+                text.trim_end_matches('.').parse().unwrap()
+            });
+            while i < code.len() && (code[i].is_ascii_alphanumeric() || code[i] == b'_') {
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Literals in the body of the first function that starts with `signature`.
+fn function_literals(src: &str, signature: &str) -> Vec<f64> {
+    let start = src
+        .find(signature)
+        .unwrap_or_else(|| panic!("{signature:?} not found"));
+    let body_start = start + src[start..].find('{').unwrap();
+    let mut depth = 0;
+    let mut body_end = body_start;
+    for (i, c) in src[body_start..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => (),
+        }
+        if depth == 0 {
+            body_end = body_start + i;
+            break;
+        }
+    }
+    literals(&src[body_start..body_end])
+}
+
+/// The hash constants, the frame shift of `hash_noise` and the `min(t, 100.0)` clamp are easy to
+/// get out of sync in ways the random scenes may not show.
+#[test]
+fn shader_literals_match() {
+    let functions = [
+        ((RUST_UTIL, "pub fn uhash("), (WGSL, "fn uhash(")),
+        ((RUST_UTIL, "pub fn hash_noise("), (WGSL, "fn hash_noise(")),
+        ((RUST_UTIL, "pub fn unormf("), (WGSL, "fn unormf(")),
+        (
+            (RUST_LIB, "pub fn add(&mut self, hit: Hit)"),
+            (WGSL, "fn add_hit("),
+        ),
+    ];
+    for ((rust_src, rust), (wgsl_src, wgsl)) in functions {
+        let expected = function_literals(rust_src, rust);
+        assert!(!expected.is_empty(), "{rust}");
+        assert_eq!(function_literals(wgsl_src, wgsl), expected, "{wgsl}");
+    }
+}
+
+#[test]
+fn literals_are_parsed() {
+    assert_eq!(
+        literals("x = (Wrapping(a) * Wrapping(0x7feb352du32)).0 ^ (x >> 16u); // 2018/07/31"),
+        [0x7feb352d as f64, 16.0]
+    );
+    assert_eq!(
+        literals("vec3<f32>(1.0, 3.402823466E+38, u32(2))"),
+        [1.0, 3.402823466E+38, 2.0]
+    );
+}
@@ -0,0 +1,61 @@
+use one_thread_gpu_bench::{reflect, roundtrip};
+use std::{borrow::Cow, collections::BTreeMap};
+use wgpu::{BufferBindingType, ShaderSource};
+
+const WGSL: &str = include_str!("../src/compute_shader.wgsl");
+const READ_WRITE: BufferBindingType = BufferBindingType::Storage { read_only: false };
+const READ_ONLY: BufferBindingType = BufferBindingType::Storage { read_only: true };
+const UNIFORM: BufferBindingType = BufferBindingType::Uniform;
+
+fn bindings(source: &str, entry_point: &str) -> Result<BTreeMap<u32, BufferBindingType>, String> {
+    reflect::bindings(&ShaderSource::Wgsl(Cow::Borrowed(source)), entry_point)
+}
+
+#[test]
+fn entry_points_only_get_the_bindings_they_use() {
+    let main = BTreeMap::from([(0, READ_WRITE), (1, UNIFORM)]);
+    assert_eq!(bindings(WGSL, "main").unwrap(), main);
+    let bvh = BTreeMap::from([
+        (0, READ_WRITE),
+        (1, UNIFORM),
+        (2, READ_ONLY),
+        (3, READ_ONLY),
+    ]);
+    assert_eq!(bindings(WGSL, "main_bvh").unwrap(), bvh);
+    // `data_u32` aliases `data`
+    assert_eq!(bindings(WGSL, "main_rng_dump").unwrap()[&0], READ_WRITE);
+
+    let spirv = roundtrip::wgsl_to_spirv(WGSL).unwrap();
+    let source = ShaderSource::SpirV(Cow::Owned(spirv));
+    assert_eq!(reflect::bindings(&source, "main_bvh").unwrap(), bvh);
+}
+
+#[test]
+fn mismatches_are_reported() {
+    let provides = BTreeMap::from([(0, READ_WRITE), (1, UNIFORM), (2, READ_ONLY)]);
+    let check = |wgsl: &str| reflect::check(&bindings(wgsl, "main")?, &provides);
+
+    let extra_input = "@group(0) @binding(0) var<storage, read_write> data: array<f32>;
+@group(0) @binding(3) var<storage, read> extra: array<f32>;
+@compute @workgroup_size(1) fn main() { data[0] = extra[0]; }";
+    assert!(check(extra_input).unwrap_err().contains("binding 3"));
+
+    let storage_settings = "@group(0) @binding(0) var<storage, read_write> data: array<f32>;
+@group(0) @binding(1) var<storage, read> settings: array<u32>;
+@compute @workgroup_size(1) fn main() { data[0] = f32(settings[0]); }";
+    assert!(check(storage_settings).unwrap_err().contains("uniform"));
+
+    let writes_input = "@group(0) @binding(2) var<storage, read_write> input: array<f32>;
+@compute @workgroup_size(1) fn main() { input[0] = 1.0; }";
+    assert!(check(writes_input).unwrap_err().contains("read-write"));
+
+    let other_group = "@group(1) @binding(0) var<storage, read_write> data: array<f32>;
+@compute @workgroup_size(1) fn main() { data[0] = 1.0; }";
+    assert!(check(other_group).unwrap_err().contains("group 1"));
+
+    let input_only = "@group(0) @binding(2) var<storage, read> input: array<f32>;
+@group(0) @binding(0) var<storage, read_write> data: array<f32>;
+@compute @workgroup_size(1) fn main() { data[0] = input[0]; }";
+    assert!(check(input_only).is_ok());
+    assert!(check("@compute @workgroup_size(1) fn nope() {}").is_err());
+}
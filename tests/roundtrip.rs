@@ -0,0 +1,48 @@
+use one_thread_gpu_bench::{roundtrip, slang_spv_path};
+use std::borrow::Cow;
+use wgpu::ShaderSource;
+
+const WGSL: &str = include_str!("../src/compute_shader.wgsl");
+
+fn entry_points(wgsl: &str) -> Vec<String> {
+    let module = naga::front::wgsl::parse_str(wgsl).expect("invalid wgsl");
+    module.entry_points.into_iter().map(|e| e.name).collect()
+}
+
+#[test]
+fn translations_keep_the_entry_points() {
+    let spirv = match roundtrip::translate(&ShaderSource::Wgsl(Cow::Borrowed(WGSL))).unwrap() {
+        ShaderSource::SpirV(words) => words,
+        _ => panic!("wgsl wasn't translated to spir-v"),
+    };
+    let wgsl = match roundtrip::translate(&ShaderSource::SpirV(spirv)).unwrap() {
+        ShaderSource::Wgsl(wgsl) => wgsl,
+        _ => panic!("spir-v wasn't translated to wgsl"),
+    };
+    assert_eq!(entry_points(&wgsl), entry_points(WGSL));
+}
+
+#[test]
+fn slang_translates_to_wgsl() {
+    let path = slang_spv_path("main");
+    let Ok(spv) = std::fs::read(&path) else {
+        println!("skipped, {path:?} not found, slangc wasn't found when building");
+        return;
+    };
+    let wgsl = roundtrip::spirv_to_wgsl(bytemuck::cast_slice(&spv)).unwrap();
+    assert_eq!(entry_points(&wgsl), ["main"]);
+}
+
+#[test]
+fn failures_are_reported() {
+    assert!(roundtrip::wgsl_to_spirv("fn main( {}").is_err());
+    assert!(roundtrip::spirv_to_wgsl(&[0x07230203, 0]).is_err());
+}
+
+#[test]
+fn names() {
+    let wgsl = ShaderSource::Wgsl(Cow::Borrowed(WGSL));
+    let spirv = ShaderSource::SpirV(Cow::Borrowed(&[]));
+    assert_eq!(roundtrip::name("wgsl", &wgsl), "wgsl -> spv");
+    assert_eq!(roundtrip::name("slang", &spirv), "slang -> wgsl");
+}
@@ -0,0 +1,52 @@
+use one_thread_gpu_bench::rust_gpu;
+use rust_gpu::RustGpuConfig;
+
+#[test]
+fn codegen_args() {
+    assert_eq!(
+        RustGpuConfig::default().codegen_args(),
+        "--dump-spirt-passes=$PWD/spirt-passes --spirt-passes=reduce,fuse_selects"
+    );
+    let no_passes = RustGpuConfig {
+        spirt_passes: &[],
+        dump_spirt_passes: false,
+        ..Default::default()
+    };
+    assert_eq!(no_passes.codegen_args(), "");
+}
+
+#[test]
+fn matrix_changes_one_setting_at_a_time() {
+    let configs = rust_gpu::matrix();
+    let mut names: Vec<_> = configs.iter().map(|c| c.name.as_str()).collect();
+    names.sort();
+    names.dedup();
+    assert_eq!(names.len(), configs.len());
+
+    let default = RustGpuConfig::default();
+    for config in &configs {
+        let changed = (config.target != default.target) as u32
+            + (config.spirt_passes != default.spirt_passes) as u32
+            + (config.panic_strategy != default.panic_strategy) as u32;
+        assert!(changed <= 1, "{}", config.name);
+        assert!(!config.dump_spirt_passes, "{}", config.name);
+    }
+    assert!(configs.iter().any(|c| c.spirt_passes.is_empty()));
+}
+
+#[test]
+fn cache_key_follows_the_settings() {
+    let default = RustGpuConfig::default();
+    assert_eq!(default.cache_key(), RustGpuConfig::default().cache_key());
+    // Only the name and the dump differ, so the module is the same
+    assert_eq!(default.cache_key(), rust_gpu::matrix()[0].cache_key());
+    let push_constant = RustGpuConfig {
+        shader_crate: rust_gpu::PUSH_CONSTANT_CRATE,
+        ..Default::default()
+    };
+    assert_ne!(default.cache_key(), push_constant.cache_key());
+    let keys: Vec<_> = rust_gpu::matrix().iter().map(|c| c.cache_key()).collect();
+    for (i, key) in keys.iter().enumerate() {
+        assert!(!keys[i + 1..].contains(key), "{i}");
+    }
+}
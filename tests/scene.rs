@@ -0,0 +1,50 @@
+use one_thread_gpu_bench::scene;
+
+const QUAD: &str = "v 0 0 0
+v 2 0 0
+v 2 2 0
+v 0 2 0
+";
+
+#[test]
+fn faces_are_fan_triangulated_and_scaled() {
+    let triangles = scene::parse_obj(&format!("{QUAD}f 1/1 2//2 3/3/3 4\n")).unwrap();
+    assert_eq!(triangles.len(), 2);
+    assert_eq!(triangles[0].a.to_array(), [-1.0, -1.0, 0.0]);
+    assert_eq!(triangles[1].c.to_array(), [-1.0, 1.0, 0.0]);
+}
+
+#[test]
+fn negative_indices_count_back_from_the_last_vertex() {
+    let relative = scene::parse_obj(&format!("{QUAD}f -4 -3 -2 -1\n")).unwrap();
+    let absolute = scene::parse_obj(&format!("{QUAD}f 1 2 3 4\n")).unwrap();
+    for (a, b) in relative.iter().zip(&absolute) {
+        assert_eq!([a.a, a.b, a.c], [b.a, b.b, b.c]);
+    }
+}
+
+#[test]
+fn malformed_files_name_the_line() {
+    let cases = [
+        (format!("{QUAD}f 0 1 2\n"), "line 5: face index 0"),
+        (
+            format!("{QUAD}f 1 2 5\n"),
+            "line 5: face index 5 out of range",
+        ),
+        (
+            format!("{QUAD}f 1 2 -5\n"),
+            "line 5: face index -5 out of range",
+        ),
+        (format!("{QUAD}f 1 a 2\n"), "line 5: invalid face index"),
+        (format!("{QUAD}f 1 2\n"), "line 5: face with less"),
+        (format!("v 0 0\n{QUAD}"), "line 1: vertex with less"),
+        (format!("{QUAD}v 0 x 0\n"), "line 5: invalid vertex"),
+        (QUAD.to_string(), "no faces"),
+    ];
+    for (src, expected) in cases {
+        let Err(err) = scene::parse_obj(&src) else {
+            panic!("{src:?} loaded");
+        };
+        assert!(err.starts_with(expected), "{err:?} for {src:?}");
+    }
+}
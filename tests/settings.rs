@@ -0,0 +1,169 @@
+use compute_shader::Stats;
+use one_thread_gpu_bench::{codegen, interpreter, reflect, roundtrip, settings};
+use settings::Settings;
+use std::borrow::Cow;
+use wgpu::ShaderSource;
+
+const WGSL: &str = include_str!("../src/compute_shader.wgsl");
+
+/// `settings` as the WGSL and GLSL kernels, with `SIZE_CONSTANT` set to `size`.
+fn sources(wgsl: &str, entry_point: &str, size: u32) -> Vec<(&'static str, ShaderSource<'static>)> {
+    let wgsl = settings::specialize_source(wgsl, size).unwrap();
+    let glsl = ShaderSource::Glsl {
+        shader: Cow::Owned(codegen::glsl(&wgsl, entry_point).unwrap()),
+        stage: naga::ShaderStage::Compute,
+        defines: Default::default(),
+    };
+    vec![
+        ("wgsl", ShaderSource::Wgsl(Cow::Owned(wgsl))),
+        ("glsl", glsl),
+    ]
+}
+
+/// Runs `entry_point` and checks it against [`compute_shader::compute`] of `size`.
+fn check(name: &str, source: &ShaderSource, entry_point: &str, size: u32, push_constants: &[u8]) {
+    let module = roundtrip::parse(source).unwrap();
+    let mut buffers = vec![vec![0; Stats::LEN * 4]];
+    interpreter::run_with_push_constants(&module, entry_point, &mut buffers, push_constants);
+    let output: Vec<f32> = bytemuck::cast_slice(&buffers[0]).to_vec();
+    let cpu = compute_shader::compute(size);
+    let gpu = Stats::read(&output, 0);
+    assert_eq!(cpu.hit_count, gpu.hit_count, "{name} size {size}");
+    assert!((cpu.sum - gpu.sum).abs() <= 1e-5 * cpu.sum.abs().max(1.0));
+}
+
+#[test]
+fn push_constants_replace_the_uniform() {
+    let wgsl = settings::with_push_constants(WGSL);
+    for (name, source) in sources(&wgsl, Settings::PushConstant.entry_point(), 0) {
+        let entry_point = match name {
+            "glsl" => "main",
+            _ => Settings::PushConstant.entry_point(),
+        };
+        let bindings = reflect::bindings(&source, entry_point).unwrap();
+        assert!(!bindings.contains_key(&1), "{name}");
+        assert_eq!(
+            reflect::push_constant_size(&source, entry_point),
+            Ok(16),
+            "{name}"
+        );
+        // `main` doesn't read them
+        if name == "wgsl" {
+            assert_eq!(reflect::push_constant_size(&source, "main"), Ok(0));
+        }
+        for size in [1, 5] {
+            let push_constants = bytemuck::cast_slice(&[size; 4]).to_vec();
+            check(name, &source, entry_point, size, &push_constants);
+        }
+    }
+}
+
+#[test]
+fn constant_settings_match_cpu() {
+    for size in [1, 5] {
+        for (name, source) in sources(WGSL, Settings::Constant.entry_point(), size) {
+            let entry_point = match name {
+                "glsl" => "main",
+                _ => Settings::Constant.entry_point(),
+            };
+            assert!(!reflect::bindings(&source, entry_point)
+                .unwrap()
+                .contains_key(&1));
+            assert_eq!(reflect::push_constant_size(&source, entry_point), Ok(0));
+            check(name, &source, entry_point, size, &[]);
+        }
+    }
+}
+
+/// naga rejects modules declaring push constants on adapters without them, even if the entry point
+/// doesn't read them.
+#[test]
+fn only_the_push_constant_variant_declares_them() {
+    let declares = |wgsl: &str| {
+        let module = naga::front::wgsl::parse_str(wgsl).unwrap();
+        let declared = module
+            .global_variables
+            .iter()
+            .any(|(_, global)| global.space == naga::AddressSpace::PushConstant);
+        declared
+    };
+    assert!(!declares(WGSL));
+    assert!(declares(&settings::with_push_constants(WGSL)));
+    assert!(!include_str!("../src/compute_shader.slang").contains("push_constant"));
+}
+
+#[test]
+fn every_language_declares_the_size() {
+    let slang = include_str!("../src/compute_shader.slang");
+    for source in [WGSL, slang] {
+        let specialized = settings::specialize_source(source, 7).unwrap();
+        let changed: Vec<_> = source
+            .lines()
+            .zip(specialized.lines())
+            .filter(|(a, b)| a != b)
+            .map(|(_, b)| b)
+            .collect();
+        assert_eq!(changed.len(), 1, "{changed:?}");
+        assert!(changed[0].trim_end_matches(';').ends_with(" 7u"));
+    }
+}
+
+#[test]
+fn kernels_without_the_size_are_reported() {
+    let wgsl = "@group(0) @binding(0) var<storage, read_write> data: array<f32>;
+@compute @workgroup_size(1) fn main() { data[0] = 1.0; }";
+    assert!(settings::specialize_source(wgsl, 1).is_err());
+}
+
+/// The words of a module declaring `%2 = OpSpecConstant %1 0` with `SpecId 0`.
+fn spec_constant_module() -> Vec<u32> {
+    vec![
+        0x07230203,
+        0x00010000,
+        0,
+        3,
+        0,
+        // OpDecorate %2 SpecId 0
+        (4 << 16) | 71,
+        2,
+        1,
+        0,
+        // %1 = OpTypeInt 32 0
+        (4 << 16) | 21,
+        1,
+        32,
+        0,
+        // %2 = OpSpecConstant %1 0
+        (4 << 16) | 50,
+        1,
+        2,
+        0,
+    ]
+}
+
+#[test]
+fn spec_constants_are_written() {
+    let mut words = spec_constant_module();
+    settings::specialize_spirv(&mut words, 0, 42).unwrap();
+    assert_eq!(words[16], 42);
+    // naga reads the default as the constant's value
+    let module = roundtrip::parse(&ShaderSource::SpirV(Cow::Owned(words))).unwrap();
+    let (_, constant) = module
+        .constants
+        .iter()
+        .find(|(_, constant)| constant.specialization == Some(0))
+        .unwrap();
+    assert_eq!(
+        constant.inner,
+        naga::ConstantInner::Scalar {
+            width: 4,
+            value: naga::ScalarValue::Uint(42)
+        }
+    );
+}
+
+#[test]
+fn missing_spec_constants_are_reported() {
+    let mut words = spec_constant_module();
+    assert!(settings::specialize_spirv(&mut words, 1, 42).is_err());
+}
@@ -0,0 +1,166 @@
+use one_thread_gpu_bench::{hash, slang, slang_spv_path};
+use slang::{SlangCompiler, SlangEmit, SlangError};
+use std::path::Path;
+
+#[test]
+fn args() {
+    let compiler = SlangCompiler {
+        opt_level: Some(3),
+        emit: Some(SlangEmit::ViaGlsl),
+        extra_flags: vec!["-g".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(
+        compiler
+            .args(Path::new("a.slang"), "main_bvh", Path::new("a.spv"))
+            .join(" "),
+        "a.slang -target spirv -profile sm_5_0 -O3 -emit-spirv-via-glsl -g -stage compute \
+         -entry main_bvh -o a.spv"
+    );
+    assert_eq!(
+        SlangCompiler::default()
+            .args(Path::new("a.slang"), "main", Path::new("a.spv"))
+            .join(" "),
+        "a.slang -target spirv -profile sm_5_0 -stage compute -entry main -o a.spv"
+    );
+}
+
+#[test]
+fn options_are_parsed() {
+    assert_eq!("direct".parse(), Ok(SlangEmit::Direct));
+    assert!("spirv".parse::<SlangEmit>().is_err());
+    assert_eq!(slang::parse_opt_level("2"), Ok(2));
+    assert!(slang::parse_opt_level("4").is_err());
+}
+
+fn compile_with(binary: &str) -> Result<(), SlangError> {
+    let dir = std::env::temp_dir().join(format!("slang-test-{binary}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let compiler = SlangCompiler {
+        binary: binary.into(),
+        ..Default::default()
+    };
+    let result = compiler.compile(Path::new("missing.slang"), "main", &dir.join("out.spv"));
+    assert!(!dir.join("out.spv").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+    result
+}
+
+#[test]
+fn failures_are_reported() {
+    assert!(matches!(
+        compile_with("this-slangc-does-not-exist"),
+        Err(SlangError::Spawn(..))
+    ));
+    if cfg!(unix) {
+        assert!(matches!(
+            compile_with("false"),
+            Err(SlangError::Failed { .. })
+        ));
+        assert!(matches!(compile_with("true"), Err(SlangError::NoOutput(_))));
+    }
+}
+
+#[test]
+fn matrix_covers_every_combination() {
+    let compilers = slang::matrix(&SlangCompiler::default());
+    assert_eq!(compilers.len(), slang::MATRIX_PROFILES.len() * 2 * 4);
+    let mut names: Vec<_> = compilers.iter().map(|c| c.name()).collect();
+    names.sort();
+    names.dedup();
+    assert_eq!(names.len(), compilers.len());
+    assert!(names.contains(&"glsl_450 -O2 via glsl".to_string()));
+}
+
+#[test]
+fn cache_is_keyed_by_source_and_settings() {
+    let dir = std::env::temp_dir().join(format!("slang-cache-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("a.slang");
+    std::fs::write(&source, "void main() {}").unwrap();
+
+    let o2 = SlangCompiler {
+        binary: "this-slangc-does-not-exist".into(),
+        opt_level: Some(2),
+        ..Default::default()
+    };
+    let o3 = SlangCompiler {
+        opt_level: Some(3),
+        ..o2.clone()
+    };
+    let path = o2.cache_path(&source, "main", &dir);
+    assert_eq!(path, o2.cache_path(&source, "main", &dir));
+    assert_ne!(path, o3.cache_path(&source, "main", &dir));
+    assert_ne!(path, o2.cache_path(&source, "main_bvh", &dir));
+
+    // A cached module is used without running slangc
+    std::fs::write(&path, [0u8; 4]).unwrap();
+    assert_eq!(o2.compile_cached(&source, "main", &dir).unwrap(), path);
+    assert!(matches!(
+        o3.compile_cached(&source, "main", &dir),
+        Err(SlangError::Spawn(..))
+    ));
+
+    std::fs::write(&source, "void main() { }").unwrap();
+    assert_ne!(path, o2.cache_path(&source, "main", &dir));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn fnv1a_reference_values() {
+    assert_eq!(hash::fnv1a(b""), 0xcbf29ce484222325);
+    assert_eq!(hash::fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+}
+
+#[test]
+fn stamps_track_the_source() {
+    let dir = std::env::temp_dir().join(format!("slang-stamp-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("a.slang");
+    let spv = dir.join("a.spv");
+    std::fs::write(&source, "void main() {}").unwrap();
+    std::fs::write(&spv, [0u8; 4]).unwrap();
+
+    assert!(!slang::is_fresh(&source, &spv), "no stamp yet");
+    slang::write_stamp(&source, &spv);
+    assert_eq!(slang::stamp_path(&spv), dir.join("a.spv.source-hash"));
+    assert!(slang::is_fresh(&source, &spv));
+    std::fs::write(&source, "void main() { }").unwrap();
+    assert!(!slang::is_fresh(&source, &spv));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn entry_points_are_found() {
+    let source = include_str!("../src/compute_shader.slang");
+    let entry_points = slang::entry_points(source);
+    for entry_point in [
+        "main",
+        "main_while",
+        "main_intersect",
+        "main_rng",
+        "main_hash_dump",
+    ] {
+        assert!(entry_points.contains(&entry_point), "{entry_point}");
+    }
+    assert_eq!(
+        entry_points.len(),
+        source.matches("[numthreads(").count(),
+        "{entry_points:?}"
+    );
+}
+
+#[test]
+fn built_modules_match_the_source() {
+    let source = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/compute_shader.slang");
+    let contents = std::fs::read_to_string(&source).unwrap();
+    for entry_point in slang::entry_points(&contents) {
+        let spv = slang_spv_path(entry_point);
+        if !spv.exists() {
+            println!("skipped, {spv:?} not found, slangc wasn't found when building");
+            return;
+        }
+        assert!(slang::is_fresh(&source, &spv), "{spv:?} is stale");
+    }
+}
@@ -0,0 +1,55 @@
+use one_thread_gpu_bench::spirv_dump;
+use spirv_dump::SpirvStats;
+use std::borrow::Cow;
+use wgpu::ShaderSource;
+
+fn wgsl_words() -> Vec<u32> {
+    let source = include_str!("../src/compute_shader.wgsl");
+    spirv_dump::spirv_words(&ShaderSource::Wgsl(Cow::Borrowed(source)))
+}
+
+#[test]
+fn stats_count_the_kernel() {
+    let words = wgsl_words();
+    let stats = SpirvStats::new(&words);
+    assert_eq!(stats.bytes, words.len() * 4);
+    assert_eq!(stats.instructions, stats.ops.values().sum::<usize>());
+    assert_eq!(stats.ops["OpFunction"], stats.functions);
+    assert_eq!(stats.ops["OpLoopMerge"], stats.loops);
+    assert!(stats.loops > 0 && stats.branches > 0 && stats.calls > 0);
+    assert_eq!(stats.histogram().lines().count(), stats.ops.len());
+}
+
+#[test]
+fn disassembly_has_every_instruction() {
+    let words = wgsl_words();
+    let disassembly = spirv_dump::disassemble(&words);
+    assert!(disassembly.starts_with("; magic 0x07230203"));
+    assert_eq!(
+        disassembly.lines().count(),
+        SpirvStats::new(&words).instructions + 1
+    );
+    assert!(disassembly
+        .lines()
+        .any(|line| line.starts_with("OpEntryPoint 5 ") && line.contains("\"main\"")));
+}
+
+#[test]
+fn spirv_is_passed_through() {
+    let words = wgsl_words();
+    let spirv = ShaderSource::SpirV(Cow::Borrowed(&words));
+    assert_eq!(spirv_dump::spirv_words(&spirv), words);
+}
+
+#[test]
+fn entry_points_only_count_what_they_call() {
+    let words = wgsl_words();
+    let module = SpirvStats::new(&words);
+    let main = SpirvStats::for_entry_point(&words, "main").unwrap();
+    let bvh = SpirvStats::for_entry_point(&words, "main_bvh").unwrap();
+    assert!(main.instructions < module.instructions && main.bytes < module.bytes);
+    assert!(main.functions >= 1 && main.functions < module.functions);
+    // BVH traversal has the stack loop on top of the pixel loops
+    assert!(bvh.loops > main.loops);
+    assert!(SpirvStats::for_entry_point(&words, "nope").is_none());
+}